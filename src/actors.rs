@@ -1,101 +1,238 @@
+use crate::bounds::Bounds;
+use crate::collision::{contact, convex_contact, convex_parts, Contact};
 use crate::config::Config;
-use crate::intersect::{lines_intersect, point_in};
-use crate::point::Point;
-use graphics::line::Line;
-use graphics::math::{rotate_radians, transform_pos};
-use graphics::polygon::Polygon;
-use graphics::{rectangle, DrawState, Transformed};
-use opengl_graphics::GlGraphics;
+use crate::geometry::{Polygon, Segment, Transform, Vec2};
+use crate::handling::Handling;
+use crate::intersect::{
+    earliest_intersection, lines_intersect, point_in, point_polygon_distance, segment_distance,
+};
+use crate::physics::split_polygon;
+use crate::render::{Color, Renderer};
 use piston::input::Key;
 use rand::{Rng, RngCore};
 use rand_distr::uniform::SampleUniform;
-use rand_distr::Normal;
 use std::f64::consts::PI;
 
-pub fn to_cartesian(theta: f64, r: f64) -> (f64, f64) {
-    return (theta.sin() * r, -theta.cos() * r);
-}
-
-pub fn to_polar(x: f64, y: f64) -> (f64, f64) {
-    return (x.atan2(-y), (x * x + y * y).sqrt());
-}
-
 fn random<T: std::cmp::PartialOrd + SampleUniform>(low: T, high: T, rng: &mut dyn RngCore) -> T {
     rng.gen_range(low..high)
 }
 
-pub fn wrapped_add(a: f64, b: f64, bound: f64) -> f64 {
-    (a + b + bound) % bound
-}
-
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct GameObject {
-    x: f64,
-    y: f64,
-    v: f64,
-    theta: f64,
+    position: Vec2,
+    velocity: Vec2,
+    // Which way the object faces, and how fast that's changing in radians
+    // per second
+    angle: f64,
+    spin: f64,
+    mass: f64,
+    // Forces applied since the last time the object moved
+    force: Vec2,
 }
 
 impl GameObject {
-    fn new(x: f64, y: f64, v: f64, theta: f64) -> GameObject {
+    pub fn new(position: Vec2, velocity: Vec2) -> GameObject {
         GameObject {
-            x: x,
-            y: y,
-            v: v,
-            theta: theta,
+            position: position,
+            velocity: velocity,
+            angle: 0.0,
+            spin: 0.0,
+            mass: 1.0,
+            force: Vec2::zero(),
         }
     }
 
-    pub fn with_go(&self, dt: f64, x_max: f64, y_max: f64) -> GameObject {
-        let (dx, dy) = to_cartesian(self.theta, self.v * dt);
-        GameObject::new(
-            wrapped_add(self.x, dx, x_max),
-            wrapped_add(self.y, dy, y_max),
-            self.v,
-            self.theta,
-        )
+    // Where the object will be after dt, or None if it left the arena for good.
+    // Any forces applied since the last move speed it up first and are then
+    // used up.
+    pub fn with_go(&self, dt: f64, bounds: &Bounds) -> Option<GameObject> {
+        let velocity = self.velocity + self.force * (dt / self.mass);
+        let (position, velocity) = bounds.advance(self.position, velocity, velocity * dt)?;
+        return Some(GameObject {
+            position: position,
+            velocity: velocity,
+            angle: self.angle + self.spin * dt,
+            force: Vec2::zero(),
+            ..*self
+        });
+    }
+
+    // A force changes the velocity more slowly the heavier the object is
+    pub fn apply_force(&mut self, force: Vec2) {
+        self.force += force;
+    }
+
+    // Speeds up the object the same whatever its mass, as gravity does
+    pub fn apply_acceleration(&mut self, acceleration: Vec2) {
+        self.apply_force(acceleration * self.mass);
+    }
+
+    // Changes the velocity straight away, as a collision does
+    pub fn apply_impulse(&mut self, impulse: Vec2) {
+        self.velocity += impulse / self.mass;
+    }
+
+    // Moves the object in place. Returns false if it left the arena for good.
+    pub fn go(&mut self, dt: f64, bounds: &Bounds) -> bool {
+        match self.with_go(dt, bounds) {
+            Some(obj) => {
+                *self = obj;
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    pub fn position(&self) -> Vec2 {
+        return self.position;
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        return self.velocity;
+    }
+
+    pub fn set_velocity(&mut self, velocity: Vec2) {
+        self.velocity = velocity;
+    }
+
+    pub fn angle(&self) -> f64 {
+        return self.angle;
+    }
+
+    pub fn spin(&self) -> f64 {
+        return self.spin;
+    }
+
+    pub fn set_spin(&mut self, spin: f64) {
+        self.spin = spin;
+    }
+
+    pub fn mass(&self) -> f64 {
+        return self.mass;
+    }
+
+    pub fn set_mass(&mut self, mass: f64) {
+        self.mass = mass;
+    }
+
+    pub fn translate(&mut self, d: Vec2, bounds: &Bounds) {
+        self.position = bounds.translate(self.position, d);
     }
 }
 
 #[derive(Clone)]
 pub struct Spaceship {
     obj: GameObject,
-    sprite_theta: f64,
+    handling: Handling,
     accel: f64,
     reverse: f64,
     left: f64,
     right: f64,
     firing: bool,
     cooldown: f64,
+    weapon: Weapon,
+    owner: OwnerId,
+}
+
+// The controls as a bot sees them, rather than as keys
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Controls {
+    // From -1 (full reverse) to 1 (full ahead)
+    pub thrust: f64,
+    // From -1 (full left) to 1 (full right)
+    pub turn: f64,
+    pub fire: bool,
 }
 
-const SPACESHIP_POINTS: [[f64; 2]; 3] = [[5.0, 7.0], [-5.0, 7.0], [0.0, -13.0]];
+// Who fired a bullet
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OwnerId(pub u32);
+
+pub const PLAYER: OwnerId = OwnerId(0);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Weapon {
+    // Muzzle speed of the bullets
+    pub speed: f64,
+    // How far bullets go before they fizzle out
+    pub range: f64,
+    // Seconds between shots
+    pub cooldown: f64,
+    // Whether bullets carry the shooter's own velocity along with them
+    pub inherit_velocity: bool,
+}
+
+const SPACESHIP_POINTS: [Vec2; 3] = [
+    Vec2 { x: 5.0, y: 7.0 },
+    Vec2 { x: -5.0, y: 7.0 },
+    SPACESHIP_NOSE,
+];
+const SPACESHIP_NOSE: Vec2 = Vec2 { x: 0.0, y: -13.0 };
+// Distance from the centre of the ship to its furthest point
+pub const SPACESHIP_RADIUS: f64 = 13.0;
 
 impl Spaceship {
     pub fn new(config: &Config) -> Spaceship {
+        let spawn = Spaceship::spawn_point(config);
         return Spaceship {
-            obj: GameObject::new(config.width() / 2.0, config.height() / 2.0, 0.0, 0.0),
-            sprite_theta: 0.0,
+            obj: GameObject::new(spawn, Vec2::zero()),
+            handling: config.handling(),
             accel: 0.0,
             reverse: 0.0,
             left: 0.0,
             right: 0.0,
             firing: false,
             cooldown: 0.0,
+            weapon: config.weapon(),
+            owner: PLAYER,
         };
     }
 
+    pub fn spawn_point(config: &Config) -> Vec2 {
+        let centre = Vec2::new(config.width() / 2.0, config.height() / 2.0);
+        return config.arena().spawn().unwrap_or(centre);
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        return self.obj.velocity();
+    }
+
+    pub fn set_velocity(&mut self, velocity: Vec2) {
+        self.obj.set_velocity(velocity);
+    }
+
+    pub fn apply_acceleration(&mut self, acceleration: Vec2) {
+        self.obj.apply_acceleration(acceleration);
+    }
+
     pub fn handle_press(&mut self, key: Key) {
         match key {
             Key::Up => self.accel = 1.0,
             Key::Down => self.reverse = 1.0,
-            Key::Left => self.left = 0.05,
-            Key::Right => self.right = 0.05,
+            Key::Left => self.left = 1.0,
+            Key::Right => self.right = 1.0,
             Key::Space => self.firing = true,
             _ => (),
         }
     }
 
+    pub fn steer(&mut self, controls: Controls) {
+        self.accel = controls.thrust.clamp(0.0, 1.0);
+        self.reverse = (-controls.thrust).clamp(0.0, 1.0);
+        self.right = controls.turn.clamp(0.0, 1.0);
+        self.left = (-controls.turn).clamp(0.0, 1.0);
+        self.firing = controls.fire;
+    }
+
+    // Whatever the ship is being steered with, as a bot would steer it
+    pub fn controls(&self) -> Controls {
+        return Controls {
+            thrust: self.accel - self.reverse,
+            turn: self.right - self.left,
+            fire: self.firing,
+        };
+    }
+
     pub fn handle_release(&mut self, key: Key) {
         match key {
             Key::Up => self.accel = 0.0,
@@ -107,32 +244,49 @@ impl Spaceship {
         }
     }
 
-    pub fn draw(&self, color: [f32; 4], ds: &DrawState, t: [[f64; 3]; 2], gl: &mut GlGraphics) {
-        Polygon::new(color).draw(
-            &SPACESHIP_POINTS,
-            ds,
-            t.trans(self.obj.x, self.obj.y).rot_rad(self.sprite_theta),
-            gl,
-        );
+    pub fn draw(&self, color: Color, bounds: &Bounds, renderer: &mut dyn Renderer) {
+        for offset in bounds.ghosts(self.position(), SPACESHIP_RADIUS) {
+            let position = self.position() + offset;
+            renderer.push_transform(Transform::placement(position, self.obj.angle()));
+            renderer.polygon(color, &SPACESHIP_POINTS);
+            renderer.pop_transform();
+        }
+    }
+
+    // Returns false if the ship left the arena
+    pub fn go(&mut self, dt: f64, bounds: &Bounds) -> bool {
+        return self.obj.go(dt, bounds);
+    }
+
+    pub fn position(&self) -> Vec2 {
+        return self.obj.position();
+    }
+
+    // Which way the ship is pointing
+    pub fn heading(&self) -> f64 {
+        return self.obj.angle();
     }
 
-    pub fn go(&mut self, dt: f64, x_max: f64, y_max: f64) {
-        self.obj = self.obj.with_go(dt, x_max, y_max);
+    pub fn owner(&self) -> OwnerId {
+        return self.owner;
     }
 
     pub fn accelerate(&mut self, dt: f64) {
-        let net_accel = (self.accel - self.reverse) * dt * 100.0;
-        let (dx, dy) = to_cartesian(self.obj.theta, self.obj.v);
-        let (ddx, ddy) = to_cartesian(self.sprite_theta, net_accel);
-        let new_dx = dx + ddx;
-        let new_dy = dy + ddy;
-        let (new_theta, new_v) = to_polar(new_dx, new_dy);
-        self.obj.v = new_v.min(200.0).max(-200.0);
-        self.obj.theta = new_theta;
+        let velocity = self.handling.thrust(
+            self.obj.velocity(),
+            self.obj.angle(),
+            self.accel - self.reverse,
+            dt,
+        );
+        self.obj.set_velocity(velocity);
     }
 
+    // Sets how fast the ship turns while it next moves
     pub fn turn(&mut self, dt: f64) {
-        self.sprite_theta += (self.right - self.left) * dt * 100.0;
+        let spin = self
+            .handling
+            .spin(self.obj.spin(), self.right - self.left, dt);
+        self.obj.set_spin(spin);
     }
 
     pub fn cooldown(&mut self, dt: f64) {
@@ -148,73 +302,262 @@ impl Spaceship {
     }
 
     pub fn fire(&mut self, bullets: &mut Vec<Bullet>) {
-        self.cooldown = 0.5;
-        bullets.push(Bullet::new(self.obj.x, self.obj.y, self.sprite_theta));
+        self.cooldown = self.weapon.cooldown;
+        let base_velocity = if self.weapon.inherit_velocity {
+            self.obj.velocity()
+        } else {
+            Vec2::zero()
+        };
+        bullets.push(Bullet::new(
+            self.nose(),
+            self.obj.angle(),
+            base_velocity,
+            &self.weapon,
+            self.owner,
+        ));
     }
 
-    pub fn edges(&self) -> Vec<[f64; 4]> {
-        let rotation_matrix = rotate_radians(self.sprite_theta);
-        let points: Vec<[f64; 2]> = SPACESHIP_POINTS
-            .iter()
-            .map(|p| transform_pos(rotation_matrix, *p))
-            .collect();
-        return points
+    // The tip of the ship, where bullets come out
+    pub fn nose(&self) -> Vec2 {
+        return self.placement().apply(SPACESHIP_NOSE);
+    }
+
+    // Whether a bullet hits the ship. The ship's own bullets only count with
+    // friendly fire on.
+    pub fn hit_by(&self, bullet: &Bullet, friendly_fire: bool) -> bool {
+        if bullet.owner == self.owner && !friendly_fire {
+            return false;
+        }
+        return point_in(bullet.coords(), self.edges().into_iter());
+    }
+
+    // From the ship's own coordinates, nose up, to the screen
+    fn placement(&self) -> Transform {
+        return Transform::placement(self.obj.position(), self.obj.angle());
+    }
+
+    fn corners(&self) -> [Vec2; 3] {
+        let placement = self.placement();
+        return SPACESHIP_POINTS.map(|p| placement.apply(p));
+    }
+
+    pub fn edges(&self) -> [Segment; 3] {
+        let corners = self.corners();
+        return std::array::from_fn(|i| Segment::new(corners[i], corners[(i + 1) % 3]));
+    }
+
+    // How the ship overlaps the asteroid, if it does, including being
+    // entirely inside it. The normal points from the ship to the asteroid.
+    pub fn contact(&self, astroid: &Astroid, bounds: &Bounds) -> Option<Contact> {
+        let corners = self.corners();
+        return astroid
+            .ghosts(SPACESHIP_RADIUS, bounds)
+            .filter_map(|offset| {
+                let at = astroid.obj.position() + offset;
+                astroid
+                    .parts
+                    .iter()
+                    .filter_map(|part| convex_contact(&corners, Vec2::zero(), part.points(), at))
+                    .max_by(|a, b| a.depth.total_cmp(&b.depth))
+            })
+            .max_by(|a, b| a.depth.total_cmp(&b.depth));
+    }
+
+    // Shortest distance between the ship's outline and the nearest copy of
+    // the asteroid's, or 0 if they touch
+    pub fn distance_to(&self, astroid: &Astroid, bounds: &Bounds) -> f64 {
+        let edges = astroid.edges_at(astroid.nearest_copy(self.position(), bounds));
+        if point_in(self.position(), edges.clone()) {
+            return 0.0;
+        }
+        return self
+            .edges()
             .iter()
-            .zip(points.iter().cycle().skip(1))
-            .map(|(p1, p2)| {
-                [
-                    p1[0] + self.obj.x,
-                    p1[1] + self.obj.y,
-                    p2[0] + self.obj.x,
-                    p2[1] + self.obj.y,
-                ]
+            .flat_map(|&edge| {
+                edges
+                    .clone()
+                    .map(move |other| segment_distance(edge, other))
             })
-            .collect();
+            .fold(f64::INFINITY, f64::min);
     }
 
-    pub fn collides<I: Iterator<Item = [f64; 4]>>(&self, edges: I) -> bool {
-        let edges_vec: Vec<[f64; 4]> = edges.collect();
+    // Edges of every copy of the ship that overlaps the screen
+    pub fn wrapped_edges(&self, bounds: &Bounds) -> impl Iterator<Item = Segment> + Clone {
+        let edges = self.edges();
+        return bounds
+            .ghosts(self.position(), SPACESHIP_RADIUS)
+            .flat_map(move |offset| edges.map(|e| e.translate(offset)));
+    }
+
+    pub fn collides<I: Iterator<Item = Segment> + Clone>(&self, edges: I) -> bool {
         return self.edges().iter().any(|edge| {
-            edges_vec
-                .iter()
-                .any(|other_edge| lines_intersect(*edge, *other_edge))
+            edges
+                .clone()
+                .any(|other_edge| lines_intersect(*edge, other_edge))
+        });
+    }
+
+    // When the ship first touched an obstacle during the last tick, as a
+    // fraction of the tick. `motion` is how far the ship moved relative to the
+    // obstacle, and both the ship and the edges are where they ended up.
+    pub fn time_of_impact<I: Iterator<Item = Segment> + Clone>(
+        &self,
+        motion: Vec2,
+        edges: I,
+    ) -> Option<f64> {
+        let ship_edges = self.edges();
+        // Corners of the ship sweeping across the obstacle, and corners of the
+        // obstacle sweeping across the ship the other way
+        let ship_corners = ship_edges.iter().filter_map(|e| {
+            let path = Segment::new(e.start - motion, e.start);
+            earliest_intersection(path, edges.clone())
         });
+        let obstacle_corners = edges.clone().filter_map(|e| {
+            let path = Segment::new(e.start + motion, e.start);
+            earliest_intersection(path, ship_edges.into_iter())
+        });
+        let earliest = ship_corners
+            .chain(obstacle_corners)
+            .fold(None, |earliest: Option<f64>, t| {
+                Some(earliest.map_or(t, |e| e.min(t)))
+            });
+        if earliest.is_none() && self.collides(edges) {
+            // Already overlapping before the tick started
+            return Some(0.0);
+        }
+        return earliest;
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Bullet {
     obj: GameObject,
+    // Distance travelled at the weapon's muzzle speed, not counting any speed
+    // carried over from the shooter
     distance: f64,
+    speed: f64,
+    range: f64,
+    owner: OwnerId,
+    // How far the bullet moved in the last tick
+    travel: Vec2,
 }
 
+const BULLET_SIZE: f64 = 2.0;
+
 impl Bullet {
-    fn new(x: f64, y: f64, theta: f64) -> Bullet {
+    pub fn new(
+        position: Vec2,
+        heading: f64,
+        base_velocity: Vec2,
+        weapon: &Weapon,
+        owner: OwnerId,
+    ) -> Bullet {
+        let velocity = Vec2::from_heading(heading, weapon.speed) + base_velocity;
+        let obj = GameObject::new(position, velocity);
         return Bullet {
-            obj: GameObject::new(x, y, 100.0, theta),
+            obj: obj,
             distance: 0.0,
+            speed: weapon.speed,
+            range: weapon.range,
+            owner: owner,
+            travel: Vec2::zero(),
         };
     }
 
-    pub fn draw(&self, color: [f32; 4], t: [[f64; 3]; 2], gl: &mut GlGraphics) {
-        rectangle(color, rectangle::square(self.obj.x, self.obj.y, 2.0), t, gl);
+    pub fn draw(&self, color: Color, bounds: &Bounds, renderer: &mut dyn Renderer) {
+        let size = Vec2::new(BULLET_SIZE, BULLET_SIZE);
+        for offset in bounds.ghosts(self.coords(), BULLET_SIZE) {
+            renderer.rectangle(color, self.obj.position() + offset, size);
+        }
     }
 
-    pub fn go(&mut self, dt: f64, x_max: f64, y_max: f64) {
-        self.obj = self.obj.with_go(dt, x_max, y_max);
-        self.distance += self.obj.v * dt;
+    // Returns false if the bullet left the arena
+    pub fn go(&mut self, dt: f64, bounds: &Bounds) -> bool {
+        let from = self.coords();
+        self.distance += self.speed * dt;
+        let inside = self.obj.go(dt, bounds);
+        self.travel = bounds.delta(from, self.coords());
+        return inside;
     }
 
     pub fn is_alive(&self) -> bool {
-        return self.distance < 100.0;
+        return self.distance < self.range;
+    }
+
+    pub fn apply_acceleration(&mut self, acceleration: Vec2) {
+        self.obj.apply_acceleration(acceleration);
     }
 
-    pub fn coords(&self) -> Point {
-        return Point::new(self.obj.x, self.obj.y);
+    // Which way the bullet is flying
+    pub fn heading(&self) -> f64 {
+        return self.obj.velocity().heading();
     }
 
-    pub fn collides(&self, astroid: &Astroid) -> bool {
-        return point_in(self.coords(), astroid.edges().iter().cloned());
+    pub fn coords(&self) -> Vec2 {
+        return self.obj.position();
+    }
+
+    // Where the bullet was partway through the last tick, from 0 at the start
+    // to 1 at the end
+    pub fn position_at(&self, t: f64) -> Vec2 {
+        return self.coords() - self.travel * (1.0 - t);
+    }
+
+    // When the bullet first hit the asteroid during the last tick, as a
+    // fraction of the tick. The whole path is checked rather than just where
+    // the bullet ended up, so fast bullets can't skip over small asteroids.
+    pub fn time_of_impact(&self, astroid: &Astroid, bounds: &Bounds) -> Option<f64> {
+        let from = self.position_at(0.0);
+        let to = self.coords();
+        let path = Segment::new(from, to);
+        let reach = self.travel.length();
+        return astroid
+            .ghosts(reach, bounds)
+            .filter_map(|offset| {
+                let edges = astroid.edges_at(offset);
+                if point_in(from, edges.clone()) {
+                    return Some(0.0);
+                }
+                return earliest_intersection(path, edges);
+            })
+            .fold(None, |earliest: Option<f64>, t| {
+                Some(earliest.map_or(t, |e| e.min(t)))
+            });
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AstroidKind {
+    Plain,
+    // Takes several hits before it breaks
+    Armored,
+    // Blows up when hit, breaking nearby asteroids and destroying the ship
+    Explosive,
+    // Breaks into three pieces instead of two
+    Splitter,
+    // Steers toward the ship
+    Magnetic,
+}
+
+impl AstroidKind {
+    // Tinted by kind, but as see-through as the plain colour
+    fn outline(self, plain: Color) -> Color {
+        let [r, g, b] = match self {
+            AstroidKind::Plain => return plain,
+            AstroidKind::Armored => [0.6, 0.7, 0.8],
+            AstroidKind::Explosive => [1.0, 0.4, 0.2],
+            AstroidKind::Splitter => [0.4, 1.0, 0.4],
+            AstroidKind::Magnetic => [0.6, 0.5, 1.0],
+        };
+        return [r, g, b, plain[3]];
+    }
+
+    // Armor shatters with the rock, everything else carries over to the pieces.
+    fn fragment_kind(self) -> AstroidKind {
+        return match self {
+            AstroidKind::Armored => AstroidKind::Plain,
+            kind => kind,
+        };
     }
 }
 
@@ -222,14 +565,26 @@ impl Bullet {
 pub struct Astroid {
     obj: GameObject,
     size: i64,
-    border: Vec<[f64; 4]>,
+    kind: AstroidKind,
+    armor: i64,
+    // Outline relative to the centre
+    border: Polygon,
+    // Distance from the centre to the furthest corner of the border
+    radius: f64,
+    // Convex pieces covering the border, relative to the centre
+    parts: Vec<Polygon>,
 }
 
 const ASTROID_LARGE: i64 = 3;
+const MIN_FRAGMENT_AREA: f64 = 1.0;
+const FRAGMENT_MIN_SPEED: f64 = 10.0;
+const FRAGMENT_MAX_SPEED: f64 = 20.0;
+const FRAGMENT_IMPACT_SPEED: f64 = 10.0;
+const MAGNETIC_MAX_SPEED: f64 = 60.0;
 
 impl Astroid {
-    pub fn large_new(config: &Config, rng: &mut dyn RngCore) -> Astroid {
-        return Self::new(ASTROID_LARGE, config, rng);
+    pub fn large_new(kind: AstroidKind, config: &Config, rng: &mut dyn RngCore) -> Astroid {
+        return Self::new(ASTROID_LARGE, kind, config, rng);
     }
 
     fn random_start(max: f64, gap: f64, rng: &mut dyn RngCore) -> f64 {
@@ -240,58 +595,299 @@ impl Astroid {
         }
     }
 
-    pub fn new(size: i64, config: &Config, mut rng: &mut dyn RngCore) -> Astroid {
+    pub fn new(
+        size: i64,
+        kind: AstroidKind,
+        config: &Config,
+        mut rng: &mut dyn RngCore,
+    ) -> Astroid {
         let radius = (size * 5) as f64;
+        let start = config
+            .arena()
+            .reachable_point(Spaceship::spawn_point(config), || {
+                Vec2::new(
+                    Astroid::random_start(config.width(), config.astroid_gap_distance(), rng),
+                    Astroid::random_start(config.height(), config.astroid_gap_distance(), rng),
+                )
+            });
+        let border = Astroid::create_border(&mut rng, radius);
+        let speed = random(40.0, 60.0, &mut rng);
+        let heading = random(0.0, 2.0 * PI, &mut rng);
+        let obj = GameObject::new(start, Vec2::from_heading(heading, speed));
+        let armor = Astroid::starting_armor(kind, config);
+        return Astroid::from_border(obj, size, kind, armor, border);
+    }
+
+    // The mass comes from the area of the border
+    fn from_border(
+        mut obj: GameObject,
+        size: i64,
+        kind: AstroidKind,
+        armor: i64,
+        border: Polygon,
+    ) -> Astroid {
+        obj.set_mass(border.area());
         return Astroid {
-            obj: GameObject::new(
-                Astroid::random_start(config.width(), config.astroid_gap_distance(), &mut rng),
-                Astroid::random_start(config.height(), config.astroid_gap_distance(), &mut rng),
-                random(40.0, 60.0, &mut rng),
-                random(0.0, 2.0 * PI, &mut rng),
-            ),
+            obj: obj,
             size: size,
-            border: Astroid::create_border(&mut rng, radius),
+            kind: kind,
+            armor: armor,
+            radius: border.radius(),
+            parts: convex_parts(&border),
+            border: border,
         };
     }
 
-    fn exploded(&self, mut rng: &mut dyn RngCore) -> Astroid {
-        let new_size = self.size - 1;
-        let radius = (new_size * 5) as f64;
-        let theta_range = Normal::new(0.0, PI / 2.0).unwrap();
-        let d_theta = rng.sample(theta_range);
-        let theta = self.obj.theta + d_theta;
-        return Astroid {
-            obj: GameObject::new(
-                self.obj.x + random(-5.0, 5.0, &mut rng),
-                self.obj.y + random(-5.0, 5.0, &mut rng),
-                random(40.0, 60.0, &mut rng),
-                theta,
-            ),
-            size: new_size,
-            border: Astroid::create_border(&mut rng, radius),
+    // A fresh asteroid drifting in from a random edge of the arena
+    pub fn stream_in(
+        size: i64,
+        kind: AstroidKind,
+        config: &Config,
+        mut rng: &mut dyn RngCore,
+    ) -> Astroid {
+        let mut astroid = Astroid::new(size, kind, config, rng);
+        let (width, height) = (config.width(), config.height());
+        let along = random(0.0, 1.0, &mut rng);
+        let (x, y) = match random(0, 4, &mut rng) {
+            0 => (along * width, 0.0),
+            1 => (width - 1.0, along * height),
+            2 => (along * width, height - 1.0),
+            _ => (0.0, along * height),
+        };
+        let toward_centre = Vec2::new(width / 2.0 - x, height / 2.0 - y).heading();
+        let speed = astroid.obj.velocity().length();
+        let heading = toward_centre + random(-PI / 4.0, PI / 4.0, &mut rng);
+        astroid.obj.position = Vec2::new(x, y);
+        astroid.obj.velocity = Vec2::from_heading(heading, speed);
+        return astroid;
+    }
+
+    fn starting_armor(kind: AstroidKind, config: &Config) -> i64 {
+        return match kind {
+            AstroidKind::Armored => config.armored_hits(),
+            _ => 1,
         };
     }
 
-    pub fn explode(&self, rng: &mut dyn RngCore) -> Vec<Astroid> {
+    fn fragment(
+        &self,
+        piece: &Polygon,
+        impact: Vec2,
+        direction: Vec2,
+        rng: &mut dyn RngCore,
+    ) -> Astroid {
+        let centroid = piece.centroid();
+        // Each piece drifts away from the cut and is pushed along by the bullet.
+        let side = if direction.cross(centroid - impact) >= 0.0 {
+            1.0
+        } else {
+            -1.0
+        };
+        let away = direction.perpendicular() * side;
+        let velocity = self.obj.velocity()
+            + away * random(FRAGMENT_MIN_SPEED, FRAGMENT_MAX_SPEED, rng)
+            + direction * FRAGMENT_IMPACT_SPEED;
+        let obj = GameObject::new(self.obj.position() + centroid, velocity);
+        let border = piece.translate(-centroid);
+        return Astroid::from_border(obj, self.size - 1, self.kind.fragment_kind(), 1, border);
+    }
+
+    // Cuts the border along the line through `impact` (in world coordinates)
+    // and returns the origin of the cut relative to the asteroid's centre
+    // along with the pieces.
+    fn cut(&self, impact: Vec2, direction: Vec2) -> (Vec2, Vec<Polygon>) {
+        let mut origin = impact - self.obj.position();
+        let (mut left, mut right) = split_polygon(&self.border, origin, direction);
+        if left.area() < MIN_FRAGMENT_AREA || right.area() < MIN_FRAGMENT_AREA {
+            // The bullet only grazed the rock, so split it through the middle.
+            origin = Vec2::zero();
+            let pieces = split_polygon(&self.border, origin, direction);
+            left = pieces.0;
+            right = pieces.1;
+        }
+        let pieces = vec![left, right]
+            .into_iter()
+            .filter(|piece| piece.area() >= MIN_FRAGMENT_AREA)
+            .collect();
+        return (origin, pieces);
+    }
+
+    // Breaks the asteroid along the bullet's path. `impact` is where the bullet
+    // hit, in world coordinates, and `heading` is the direction it was travelling.
+    // The pieces are added to `fragments`.
+    pub fn explode(
+        &self,
+        impact: Vec2,
+        heading: f64,
+        rng: &mut dyn RngCore,
+        fragments: &mut Vec<Astroid>,
+    ) {
         if self.size <= 1 {
-            vec![]
+            return;
+        }
+        let direction = Vec2::from_heading(heading, 1.0);
+        let (origin, mut pieces) = self.cut(impact, direction);
+        if self.kind == AstroidKind::Splitter {
+            // The larger half is cut again across the bullet's path.
+            pieces.sort_by(|a, b| b.area().partial_cmp(&a.area()).unwrap());
+            if let Some(largest) = pieces.first().cloned() {
+                let across = direction.perpendicular();
+                let (front, back) = split_polygon(&largest, largest.centroid(), across);
+                pieces.remove(0);
+                pieces.extend(
+                    vec![front, back]
+                        .into_iter()
+                        .filter(|piece| piece.area() >= MIN_FRAGMENT_AREA),
+                );
+            }
+        }
+        fragments.extend(
+            pieces
+                .iter()
+                .map(|piece| self.fragment(piece, origin, direction, rng)),
+        );
+    }
+
+    // Hits the asteroid, returning false if it broke. Armored asteroids lose
+    // a layer of armor until they run out, and explosive ones are destroyed
+    // outright; the blast is left to the caller. Anything else breaks into
+    // `fragments`.
+    pub fn hit(
+        &mut self,
+        impact: Vec2,
+        heading: f64,
+        rng: &mut dyn RngCore,
+        fragments: &mut Vec<Astroid>,
+    ) -> bool {
+        if self.armor > 1 {
+            self.armor -= 1;
+            return true;
+        }
+        if self.kind != AstroidKind::Explosive {
+            self.explode(impact, heading, rng, fragments);
+        }
+        return false;
+    }
+
+    pub fn is_explosive(&self) -> bool {
+        return self.kind == AstroidKind::Explosive && self.armor <= 1;
+    }
+
+    // Magnetic asteroids accelerate toward the target, taking the shortest way
+    // around the edges of the screen.
+    pub fn steer_toward(&mut self, target: Vec2, pull: f64, dt: f64, bounds: &Bounds) {
+        if self.kind != AstroidKind::Magnetic {
+            return;
+        }
+        let d = bounds.delta(self.obj.position(), target);
+        let distance = d.length();
+        if distance == 0.0 {
+            return;
+        }
+        let velocity = self.obj.velocity() + d * (pull * dt / distance);
+        let speed = velocity.length();
+        if speed > MAGNETIC_MAX_SPEED {
+            self.obj
+                .set_velocity(velocity * (MAGNETIC_MAX_SPEED / speed));
         } else {
-            vec![self.exploded(rng), self.exploded(rng)]
+            self.obj.set_velocity(velocity);
         }
     }
 
-    pub fn draw(&self, color: [f32; 4], ds: &DrawState, t: [[f64; 3]; 2], gl: &mut GlGraphics) {
-        let line_info = Line::new(color, 0.5);
-        for line_points in self.border.iter() {
-            line_info.draw(*line_points, ds, t.trans(self.obj.x, self.obj.y), gl);
+    pub fn draw(&self, color: Color, bounds: &Bounds, renderer: &mut dyn Renderer) {
+        let outline = self.kind.outline(color);
+        for offset in self.ghosts(0.0, bounds) {
+            let position = self.obj.position() + offset;
+            renderer.push_transform(Transform::translation(position));
+            for edge in self.border.edges() {
+                renderer.line(outline, self.armor as f64, edge);
+            }
+            renderer.pop_transform();
         }
     }
 
-    pub fn go(&mut self, dt: f64, x_max: f64, y_max: f64) {
-        self.obj = self.obj.with_go(dt, x_max, y_max);
+    pub fn radius(&self) -> f64 {
+        return self.radius;
+    }
+
+    // Offsets of the copies of this asteroid that overlap the screen, or come
+    // within `margin` of it.
+    pub fn ghosts(&self, margin: f64, bounds: &Bounds) -> impl Iterator<Item = Vec2> + Clone {
+        return bounds.ghosts(self.obj.position(), self.radius() + margin);
+    }
+
+    // Offset of the copy of this asteroid closest to the point
+    fn nearest_copy(&self, point: Vec2, bounds: &Bounds) -> Vec2 {
+        let position = self.obj.position();
+        return point + bounds.delta(point, position) - position;
+    }
+
+    // How far the point is from the nearest copy of the outline, or 0 if
+    // it's inside
+    pub fn distance_from(&self, point: Vec2, bounds: &Bounds) -> f64 {
+        let offset = self.nearest_copy(point, bounds);
+        return point_polygon_distance(point, self.edges_at(offset));
+    }
+
+    // Edges of every copy of this asteroid within `margin` of the screen
+    pub fn wrapped_edges(
+        &self,
+        margin: f64,
+        bounds: &Bounds,
+    ) -> impl Iterator<Item = Segment> + Clone + '_ {
+        return self
+            .ghosts(margin, bounds)
+            .flat_map(move |offset| self.edges_at(offset));
+    }
+
+    // Returns false if the asteroid left the arena
+    pub fn go(&mut self, dt: f64, bounds: &Bounds) -> bool {
+        return self.obj.go(dt, bounds);
     }
 
-    pub fn create_border(mut rng: &mut dyn RngCore, radius: f64) -> Vec<[f64; 4]> {
+    pub fn kind(&self) -> AstroidKind {
+        return self.kind;
+    }
+
+    pub fn size(&self) -> i64 {
+        return self.size;
+    }
+
+    pub fn obj(&self) -> &GameObject {
+        return &self.obj;
+    }
+
+    pub fn obj_mut(&mut self) -> &mut GameObject {
+        return &mut self.obj;
+    }
+
+    pub fn mass(&self) -> f64 {
+        return self.obj.mass();
+    }
+
+    // How the other asteroid overlaps this one, if it does, taking the
+    // closest way around the edges of the screen. The normal points from this
+    // asteroid to the other.
+    pub fn contact(&self, other: &Astroid, bounds: &Bounds) -> Option<Contact> {
+        return other
+            .ghosts(self.radius(), bounds)
+            .filter_map(|offset| {
+                contact(
+                    &self.parts,
+                    self.obj.position(),
+                    &other.parts,
+                    other.obj.position() + offset,
+                )
+            })
+            .max_by(|a, b| a.depth.total_cmp(&b.depth));
+    }
+
+    #[cfg(test)]
+    pub fn collides_with(&self, other: &Astroid, bounds: &Bounds) -> bool {
+        return self.contact(other, bounds).is_some();
+    }
+
+    pub fn create_border(mut rng: &mut dyn RngCore, radius: f64) -> Polygon {
         let spread = radius / 5.0;
         let point_count = random(8, 12, &mut rng);
         let mut points = Vec::with_capacity(point_count);
@@ -299,80 +895,474 @@ impl Astroid {
         let corner_i_to_theta = |i| theta_0 + 2.0 * PI * i as f64 / point_count as f64;
         for theta in (1..point_count + 1).map(corner_i_to_theta) {
             let distance = radius + random(-spread, spread, &mut rng);
-            points.push(to_cartesian(theta, distance));
-        }
-        let point = points[0];
-        points.push(point);
-        let mut lines = Vec::with_capacity(point_count);
-        for point in points.iter().zip(points.iter().skip(1)) {
-            let (&(x1, x2), &(y1, y2)) = point;
-            lines.push([x1, x2, y1, y2]);
+            points.push(Vec2::from_heading(theta, distance));
         }
-        return lines;
+        return Polygon::new(points);
     }
 
-    pub fn edges(&self) -> Vec<[f64; 4]> {
+    pub fn edges(&self) -> impl Iterator<Item = Segment> + Clone + '_ {
+        return self.edges_at(Vec2::zero());
+    }
+
+    pub fn edges_at(&self, offset: Vec2) -> impl Iterator<Item = Segment> + Clone + '_ {
+        let position = self.obj.position() + offset;
         return self
             .border
-            .iter()
-            .map(|edge| {
-                [
-                    edge[0] + self.obj.x,
-                    edge[1] + self.obj.y,
-                    edge[2] + self.obj.x,
-                    edge[3] + self.obj.y,
-                ]
-            })
-            .collect();
+            .edges()
+            .map(move |edge| edge.translate(position));
+    }
+
+    #[cfg(test)]
+    pub fn with_border(obj: GameObject, size: i64, border: Polygon) -> Astroid {
+        return Astroid::from_border(obj, size, AstroidKind::Plain, 1, border);
+    }
+
+    #[cfg(test)]
+    pub fn armored_with_border(obj: GameObject, size: i64, armor: i64, border: Polygon) -> Astroid {
+        return Astroid::from_border(obj, size, AstroidKind::Armored, armor, border);
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::bounds::{Boundary, TEST_BOUNDS};
     use expectest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
     use std::f64::consts::PI;
 
     #[test]
-    fn test_wrapped_add() {
-        assert_eq!(wrapped_add(1.0, 1.0, 10.0), 2.0);
-        assert_eq!(wrapped_add(1.0, 10.0, 10.0), 1.0);
-        assert_eq!(wrapped_add(1.0, -5.0, 10.0), 6.0);
+    fn test_game_object_factories() {
+        expect!(GameObject::new(Vec2::new(2.0, 3.0), Vec2::new(5.0, 7.0))).to(be_equal_to(
+            GameObject {
+                position: Vec2::new(2.0, 3.0),
+                velocity: Vec2::new(5.0, 7.0),
+                angle: 0.0,
+                spin: 0.0,
+                mass: 1.0,
+                force: Vec2::zero(),
+            },
+        ));
+    }
+
+    #[test]
+    fn test_game_object_go() {
+        let obj = GameObject::new(Vec2::new(2.0, 3.0), Vec2::new(0.0, -1.0));
+        expect!(obj.with_go(1.0, &TEST_BOUNDS)).to(be_equal_to(Some(GameObject::new(
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, -1.0),
+        ))));
+        expect!(obj.with_go(200.0, &TEST_BOUNDS)).to(be_equal_to(Some(GameObject::new(
+            Vec2::new(2.0, 3.0),
+            Vec2::new(0.0, -1.0),
+        ))));
     }
 
-    fn expect_both_close_to((x, y): (f64, f64), (x_expected, y_expected): (f64, f64)) {
-        expect!(x).to(be_close_to(x_expected));
-        expect!(y).to(be_close_to(y_expected));
+    fn square_astroid(size: i64) -> Astroid {
+        return Astroid::with_border(
+            GameObject::new(Vec2::new(100.0, 100.0), Vec2::zero()),
+            size,
+            Polygon::square(5.0),
+        );
     }
 
     #[test]
-    fn test_to_cartesian() {
-        expect_both_close_to(to_cartesian(0.0, 1.0), (0.0, -1.0));
-        expect_both_close_to(to_cartesian(PI / 2.0, 1.0), (1.0, 0.0));
+    fn test_game_object_forces() {
+        let mut obj = GameObject::new(Vec2::new(100.0, 100.0), Vec2::zero());
+        obj.apply_force(Vec2::new(3.0, 0.0));
+        obj.apply_force(Vec2::new(1.0, 0.0));
+        let moved = obj.with_go(0.5, &TEST_BOUNDS).unwrap();
+        expect!(moved.velocity().x).to(be_close_to(2.0));
+        expect!(moved.position().x).to(be_close_to(101.0));
+        // Forces only last for one move
+        let coasting = moved.with_go(0.5, &TEST_BOUNDS).unwrap();
+        expect!(coasting.velocity().x).to(be_close_to(2.0));
+        expect!(coasting.position().x).to(be_close_to(102.0));
     }
 
     #[test]
-    fn test_to_polar() {
-        expect_both_close_to(to_polar(0.0, -1.0), (0.0, 1.0));
-        expect_both_close_to(to_polar(1.0, 0.0), (PI / 2.0, 1.0));
+    fn test_game_object_mass() {
+        let mut heavy = GameObject::new(Vec2::new(100.0, 100.0), Vec2::zero());
+        heavy.set_mass(4.0);
+        // Forces and impulses move heavy things less, accelerations don't
+        heavy.apply_force(Vec2::new(8.0, 0.0));
+        heavy.apply_acceleration(Vec2::new(0.0, 2.0));
+        let moved = heavy.with_go(1.0, &TEST_BOUNDS).unwrap();
+        expect!(moved.velocity()).to(be_equal_to(Vec2::new(2.0, 2.0)));
+        heavy.apply_impulse(Vec2::new(-4.0, 0.0));
+        expect!(heavy.velocity()).to(be_equal_to(Vec2::new(-1.0, 0.0)));
     }
 
     #[test]
-    fn test_game_object_factories() {
-        expect!(GameObject::new(2.0, 3.0, 5.0, 7.0)).to(be_equal_to(GameObject {
-            x: 2.0,
-            y: 3.0,
-            v: 5.0,
-            theta: 7.0,
-        }));
+    fn test_game_object_spin() {
+        let mut obj = GameObject::new(Vec2::new(100.0, 100.0), Vec2::new(10.0, 0.0));
+        obj.set_spin(PI);
+        let moved = obj.with_go(0.5, &TEST_BOUNDS).unwrap();
+        expect!(moved.angle()).to(be_close_to(PI / 2.0));
+        // Turning doesn't change which way it's moving
+        expect!(moved.velocity()).to(be_equal_to(Vec2::new(10.0, 0.0)));
     }
 
     #[test]
-    fn test_game_object_go() {
-        let obj = GameObject::new(2.0, 3.0, 1.0, 0.0);
-        expect!(obj.with_go(1.0, 200.0, 200.0))
-            .to(be_equal_to(GameObject::new(2.0, 2.0, 1.0, 0.0)));
-        expect!(obj.with_go(200.0, 200.0, 200.0))
-            .to(be_equal_to(GameObject::new(2.0, 3.0, 1.0, 0.0)));
+    fn test_explode_cuts_along_bullet_path() {
+        let astroid = square_astroid(2);
+        let mut rng = StdRng::seed_from_u64(1);
+        // A bullet flying straight up through x = 102
+        let mut fragments = Vec::new();
+        astroid.explode(Vec2::new(102.0, 105.0), 0.0, &mut rng, &mut fragments);
+        expect!(fragments.len()).to(be_equal_to(2));
+        let total: f64 = fragments.iter().map(Astroid::mass).sum();
+        expect!(total).to(be_close_to(astroid.mass()));
+        let mut areas: Vec<f64> = fragments.iter().map(Astroid::mass).collect();
+        areas.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expect!(areas[0]).to(be_close_to(30.0));
+        expect!(areas[1]).to(be_close_to(70.0));
+        for fragment in fragments.iter() {
+            expect!(fragment.size).to(be_equal_to(1));
+            // Pieces still fit together where the parent was
+            for edge in fragment.edges() {
+                assert!(
+                    (95.0..=105.0).contains(&edge.start.x)
+                        && (95.0..=105.0).contains(&edge.start.y)
+                );
+            }
+            // Pieces drift apart sideways and are pushed along the bullet's path
+            let v = fragment.obj.velocity();
+            let side = fragment.obj.position().x - 102.0;
+            assert!(v.x * side > 0.0);
+            assert!(v.y < 0.0);
+        }
+    }
+
+    #[test]
+    fn test_explode_grazing_shot_splits_through_middle() {
+        let astroid = square_astroid(3);
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut fragments = Vec::new();
+        astroid.explode(Vec2::new(105.0, 105.0), 0.0, &mut rng, &mut fragments);
+        expect!(fragments.len()).to(be_equal_to(2));
+        for fragment in fragments.iter() {
+            expect!(fragment.mass()).to(be_close_to(50.0));
+        }
+    }
+
+    #[test]
+    fn test_explode_smallest_astroid() {
+        let astroid = square_astroid(1);
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut fragments = Vec::new();
+        astroid.explode(Vec2::new(100.0, 100.0), 0.0, &mut rng, &mut fragments);
+        expect!(fragments.len()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_armored_takes_several_hits() {
+        let mut astroid = square_astroid(2);
+        astroid.kind = AstroidKind::Armored;
+        astroid.armor = 3;
+        let mut rng = StdRng::seed_from_u64(4);
+        let impact = Vec2::new(100.0, 105.0);
+        let mut fragments = Vec::new();
+        assert!(astroid.hit(impact, 0.0, &mut rng, &mut fragments));
+        assert!(astroid.hit(impact, 0.0, &mut rng, &mut fragments));
+        expect!(fragments.len()).to(be_equal_to(0));
+        assert!(!astroid.hit(impact, 0.0, &mut rng, &mut fragments));
+        expect!(fragments.len()).to(be_equal_to(2));
+        for fragment in fragments.iter() {
+            expect!(fragment.kind).to(be_equal_to(AstroidKind::Plain));
+        }
+    }
+
+    #[test]
+    fn test_explosive_is_destroyed() {
+        let mut astroid = square_astroid(3);
+        astroid.kind = AstroidKind::Explosive;
+        let mut rng = StdRng::seed_from_u64(5);
+        assert!(astroid.is_explosive());
+        let mut fragments = Vec::new();
+        assert!(!astroid.hit(Vec2::new(100.0, 105.0), 0.0, &mut rng, &mut fragments));
+        expect!(fragments.len()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_splitter_breaks_into_three() {
+        let mut astroid = square_astroid(2);
+        astroid.kind = AstroidKind::Splitter;
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut fragments = Vec::new();
+        let impact = Vec2::new(102.0, 105.0);
+        assert!(!astroid.clone().hit(impact, 0.0, &mut rng, &mut fragments));
+        expect!(fragments.len()).to(be_equal_to(3));
+        let total: f64 = fragments.iter().map(Astroid::mass).sum();
+        expect!(total).to(be_close_to(astroid.mass()));
+        for fragment in fragments.iter() {
+            expect!(fragment.kind).to(be_equal_to(AstroidKind::Splitter));
+        }
+    }
+
+    #[test]
+    fn test_magnetic_steers_toward_target() {
+        let mut astroid = square_astroid(2);
+        let target = Vec2::new(150.0, 100.0);
+        astroid.steer_toward(target, 30.0, 1.0, &TEST_BOUNDS);
+        expect!(astroid.obj.velocity().x).to(be_close_to(0.0));
+        astroid.kind = AstroidKind::Magnetic;
+        astroid.steer_toward(target, 30.0, 1.0, &TEST_BOUNDS);
+        expect!(astroid.obj.velocity().x).to(be_close_to(30.0));
+        // The shortest way to a target behind the left edge is through it
+        astroid.steer_toward(
+            Vec2::new(190.0, 100.0),
+            30.0,
+            1.0,
+            &Bounds::new(150.0, 200.0, Boundary::Wrap),
+        );
+        expect!(astroid.obj.velocity().x).to(be_close_to(0.0));
+        for _ in 0..10 {
+            astroid.steer_toward(target, 30.0, 1.0, &TEST_BOUNDS);
+        }
+        expect!(astroid.obj.velocity().x).to(be_close_to(MAGNETIC_MAX_SPEED));
+    }
+
+    fn ship_at(x: f64, y: f64) -> Spaceship {
+        let mut ship = Spaceship::new(&Config::new());
+        ship.obj = GameObject::new(Vec2::new(x, y), Vec2::zero());
+        return ship;
+    }
+
+    fn astroid_at(x: f64, y: f64) -> Astroid {
+        let mut astroid = square_astroid(2);
+        astroid.obj.position = Vec2::new(x, y);
+        return astroid;
+    }
+
+    fn bullet_hits(bullet: &Bullet, astroid: &Astroid) -> bool {
+        return bullet.time_of_impact(astroid, &TEST_BOUNDS).is_some();
+    }
+
+    fn ship_hits(ship: &Spaceship, astroid: &Astroid) -> bool {
+        let edges = astroid.wrapped_edges(SPACESHIP_RADIUS, &TEST_BOUNDS);
+        return ship.collides(edges);
+    }
+
+    #[test]
+    fn test_ship_collides_across_edges() {
+        // Ship at the right edge, asteroid straddling the left
+        assert!(ship_hits(&ship_at(198.0, 100.0), &astroid_at(3.0, 100.0)));
+        // Ship at the left edge, asteroid straddling the right
+        assert!(ship_hits(&ship_at(2.0, 100.0), &astroid_at(197.0, 100.0)));
+        // Ship's nose poking over the top, asteroid at the bottom
+        assert!(ship_hits(&ship_at(100.0, 3.0), &astroid_at(100.0, 192.0)));
+        // Ship at the bottom, asteroid straddling the top
+        assert!(ship_hits(&ship_at(100.0, 196.0), &astroid_at(102.0, 4.0)));
+        // Ship in one corner, asteroid in the opposite one
+        assert!(ship_hits(&ship_at(198.0, 198.0), &astroid_at(3.0, 3.0)));
+        assert!(ship_hits(&ship_at(2.0, 2.0), &astroid_at(196.0, 196.0)));
+        assert!(ship_hits(&ship_at(198.0, 3.0), &astroid_at(3.0, 196.0)));
+        assert!(ship_hits(&ship_at(2.0, 196.0), &astroid_at(197.0, 3.0)));
+        // Far apart on the torus too
+        assert!(!ship_hits(&ship_at(100.0, 100.0), &astroid_at(3.0, 100.0)));
+        assert!(!ship_hits(&ship_at(170.0, 100.0), &astroid_at(3.0, 100.0)));
+    }
+
+    fn bullet_at(x: f64, y: f64) -> Bullet {
+        let weapon = Config::new().weapon();
+        return Bullet::new(Vec2::new(x, y), 0.0, Vec2::zero(), &weapon, PLAYER);
+    }
+
+    #[test]
+    fn test_bullets_leave_from_the_nose() {
+        let mut ship = ship_at(100.0, 100.0);
+        ship.obj.angle = PI / 2.0;
+        let mut bullets = Vec::new();
+        ship.fire(&mut bullets);
+        expect!(bullets[0].coords().x).to(be_close_to(113.0));
+        expect!(bullets[0].coords().y).to(be_close_to(100.0));
+        expect!(bullets[0].owner).to(be_equal_to(PLAYER));
+    }
+
+    #[test]
+    fn test_bullets_inherit_ship_velocity() {
+        let mut ship = ship_at(100.0, 100.0);
+        ship.obj = GameObject::new(Vec2::new(100.0, 100.0), Vec2::new(0.0, -200.0));
+        let mut bullets = Vec::new();
+        ship.fire(&mut bullets);
+        let v = bullets[0].obj.velocity();
+        expect!(v.y).to(be_close_to(-300.0));
+        // A fast bullet fizzles out at the same time as a slow one
+        for _ in 0..3 {
+            bullets[0].go(0.25, &TEST_BOUNDS);
+        }
+        assert!(bullets[0].is_alive());
+        bullets[0].go(0.25, &TEST_BOUNDS);
+        assert!(!bullets[0].is_alive());
+
+        ship.weapon.inherit_velocity = false;
+        ship.fire(&mut bullets);
+        expect!(bullets[1].obj.velocity().y).to(be_close_to(-100.0));
+    }
+
+    #[test]
+    fn test_friendly_fire() {
+        let ship = ship_at(100.0, 100.0);
+        let own = bullet_at(100.0, 100.0);
+        assert!(!ship.hit_by(&own, false));
+        assert!(ship.hit_by(&own, true));
+        let mut enemy = bullet_at(100.0, 100.0);
+        enemy.owner = OwnerId(1);
+        assert!(ship.hit_by(&enemy, false));
+        enemy.obj = GameObject::new(Vec2::new(150.0, 100.0), Vec2::zero());
+        assert!(!ship.hit_by(&enemy, false));
+    }
+
+    #[test]
+    fn test_bullet_collides_across_edges() {
+        let astroid = astroid_at(3.0, 3.0);
+        assert!(bullet_hits(&bullet_at(199.0, 3.0), &astroid));
+        assert!(bullet_hits(&bullet_at(3.0, 199.0), &astroid));
+        assert!(bullet_hits(&bullet_at(199.0, 199.0), &astroid));
+        assert!(!bullet_hits(&bullet_at(190.0, 190.0), &astroid));
+    }
+
+    fn fast_weapon() -> Weapon {
+        return Weapon {
+            speed: 1500.0,
+            range: 1000.0,
+            cooldown: 0.5,
+            inherit_velocity: false,
+        };
+    }
+
+    #[test]
+    fn test_fast_bullets_dont_tunnel() {
+        let mut ship = ship_at(100.0, 140.0);
+        ship.weapon = fast_weapon();
+        let mut bullets = Vec::new();
+        ship.fire(&mut bullets);
+        let astroid = astroid_at(100.0, 100.0);
+        // Starts at the nose, 22 below the asteroid, and ends up 50 further
+        // on, past the far side.
+        bullets[0].go(1.0 / 30.0, &TEST_BOUNDS);
+        expect!(bullets[0].coords().y).to(be_close_to(77.0));
+        assert!(!point_in(bullets[0].coords(), astroid.edges()));
+        let t = bullets[0].time_of_impact(&astroid, &TEST_BOUNDS).unwrap();
+        expect!(t).to(be_close_to(0.44));
+        expect!(bullets[0].position_at(t).y).to(be_close_to(105.0));
+    }
+
+    #[test]
+    fn test_fast_bullets_dont_tunnel_across_edges() {
+        let mut bullet = Bullet::new(
+            Vec2::new(180.0, 3.0),
+            PI / 2.0,
+            Vec2::zero(),
+            &fast_weapon(),
+            PLAYER,
+        );
+        // Wraps around the right edge and passes straight through the
+        // asteroid on the left
+        bullet.go(1.0 / 30.0, &TEST_BOUNDS);
+        expect!(bullet.coords().x).to(be_close_to(30.0));
+        let t = bullet
+            .time_of_impact(&astroid_at(3.0, 3.0), &TEST_BOUNDS)
+            .unwrap();
+        expect!(bullet.position_at(t).x).to(be_close_to(-2.0));
+        assert!(!bullet_hits(&bullet, &astroid_at(3.0, 100.0)));
+    }
+
+    #[test]
+    fn test_fast_ship_doesnt_tunnel() {
+        let astroid = astroid_at(100.0, 100.0);
+        let edges = astroid.wrapped_edges(SPACESHIP_RADIUS, &TEST_BOUNDS);
+        // Ended up above the asteroid after moving up 80 in one tick
+        let ship = ship_at(100.0, 60.0);
+        assert!(!ship.collides(edges.clone()));
+        let t = ship
+            .time_of_impact(Vec2::new(0.0, -80.0), edges.clone())
+            .unwrap();
+        expect!(t).to(be_close_to(0.275));
+        // Passing alongside
+        let ship = ship_at(130.0, 60.0);
+        expect!(ship.time_of_impact(Vec2::new(0.0, -80.0), edges.clone())).to(be_none());
+        // Sitting still on top of it
+        let ship = ship_at(100.0, 100.0);
+        expect!(ship.time_of_impact(Vec2::zero(), edges)).to(be_some());
+    }
+
+    #[test]
+    fn test_astroids_collide_across_edges() {
+        assert!(astroid_at(2.0, 100.0).collides_with(&astroid_at(195.0, 100.0), &TEST_BOUNDS));
+        assert!(astroid_at(100.0, 2.0).collides_with(&astroid_at(100.0, 195.0), &TEST_BOUNDS));
+        assert!(astroid_at(2.0, 2.0).collides_with(&astroid_at(195.0, 195.0), &TEST_BOUNDS));
+        assert!(!astroid_at(2.0, 100.0).collides_with(&astroid_at(185.0, 100.0), &TEST_BOUNDS));
+    }
+
+    #[test]
+    fn test_ship_inside_astroid_collides() {
+        let big = Polygon::new(vec![
+            Vec2::new(-40.0, -40.0),
+            Vec2::new(40.0, -40.0),
+            Vec2::new(40.0, 40.0),
+            Vec2::new(-40.0, 40.0),
+        ]);
+        let astroid = Astroid::with_border(
+            GameObject::new(Vec2::new(100.0, 100.0), Vec2::zero()),
+            3,
+            big,
+        );
+        let ship = ship_at(100.0, 100.0);
+        // No edges cross, but the ship is still inside
+        assert!(!ship_hits(&ship, &astroid));
+        let contact = ship.contact(&astroid, &TEST_BOUNDS).unwrap();
+        expect!(contact.points.len()).to(be_equal_to(3));
+        // The quickest way out is sideways, past the ship's wings
+        expect!(contact.depth).to(be_close_to(45.0));
+        expect!(contact.normal.y).to(be_close_to(0.0));
+    }
+
+    #[test]
+    fn test_distances() {
+        // The bottom of the ship is at y = 107 and the top of the asteroid at 115
+        let ship = ship_at(100.0, 100.0);
+        expect!(ship.distance_to(&astroid_at(100.0, 120.0), &TEST_BOUNDS)).to(be_close_to(8.0));
+        expect!(ship.distance_to(&astroid_at(100.0, 100.0), &TEST_BOUNDS)).to(be_equal_to(0.0));
+        // Measured to the copy of the asteroid past the bottom of the screen
+        let ship = ship_at(100.0, 195.0);
+        expect!(ship.distance_to(&astroid_at(100.0, 12.0), &TEST_BOUNDS)).to(be_close_to(5.0));
+        let astroid = astroid_at(130.0, 100.0);
+        expect!(astroid.distance_from(Vec2::new(100.0, 100.0), &TEST_BOUNDS)).to(be_close_to(25.0));
+        expect!(astroid.distance_from(Vec2::new(130.0, 102.0), &TEST_BOUNDS)).to(be_equal_to(0.0));
+        // Round the side of the screen
+        let astroid = astroid_at(180.0, 100.0);
+        expect!(astroid.distance_from(Vec2::new(10.0, 100.0), &TEST_BOUNDS)).to(be_close_to(25.0));
+    }
+
+    #[test]
+    fn test_ship_contact_across_edges() {
+        let contact = ship_at(198.0, 100.0).contact(&astroid_at(3.0, 100.0), &TEST_BOUNDS);
+        // The asteroid is to the right, round the edge of the screen
+        assert!(contact.unwrap().normal.x > 0.0);
+        expect!(ship_at(100.0, 100.0).contact(&astroid_at(3.0, 100.0), &TEST_BOUNDS)).to(be_none());
+    }
+
+    #[test]
+    fn test_concave_astroids_contact() {
+        // A C opening to the right, with a small square in the gap
+        let c = Polygon::new(vec![
+            Vec2::new(-10.0, -10.0),
+            Vec2::new(10.0, -10.0),
+            Vec2::new(10.0, -5.0),
+            Vec2::new(-5.0, -5.0),
+            Vec2::new(-5.0, 5.0),
+            Vec2::new(10.0, 5.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(-10.0, 10.0),
+        ]);
+        let c = Astroid::with_border(GameObject::new(Vec2::new(100.0, 100.0), Vec2::zero()), 3, c);
+        expect!(c.contact(&astroid_at(104.0, 100.0), &TEST_BOUNDS)).to(be_none());
+        // Pushed against the back of the C
+        let contact = c.contact(&astroid_at(99.0, 100.0), &TEST_BOUNDS).unwrap();
+        expect!(contact.depth).to(be_close_to(1.0));
+        expect!(contact.normal).to(be_equal_to(Vec2::new(1.0, 0.0)));
     }
 }
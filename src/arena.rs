@@ -0,0 +1,266 @@
+use crate::bounds::Bounds;
+use crate::geometry::{Polygon, Segment, Vec2};
+use crate::gravity::GravityWell;
+use crate::intersect::lines_intersect;
+use crate::render::{Color, Renderer};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+
+// Static walls that ships, bullets and asteroids can't pass through.
+//
+// Levels are text files with one shape per line:
+//
+//     # A comment
+//     spawn x y                  where the ship starts
+//     wall x1 y1 x2 y2           a single wall
+//     polygon x1 y1 x2 y2 ...    a closed outline
+//     circle x y radius sides    a regular polygon with that many sides, from
+//                                3 to MAX_CIRCLE_SIDES
+//     well x y strength horizon  a gravity well, see GravityWell
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arena {
+    walls: Vec<Segment>,
+    wells: Vec<GravityWell>,
+    spawn: Option<Vec2>,
+}
+
+const SPAWN_ATTEMPTS: usize = 20;
+// Most sides a circle can have, far more than can be told apart on screen
+const MAX_CIRCLE_SIDES: f64 = 1024.0;
+
+fn parse_numbers(line_number: usize, words: &[&str]) -> Result<Vec<f64>, String> {
+    return words
+        .iter()
+        .map(|word| {
+            word.parse::<f64>()
+                .map_err(|_| format!("line {}: {} isn't a number", line_number, word))
+        })
+        .collect();
+}
+
+impl Arena {
+    pub fn empty() -> Arena {
+        return Arena {
+            walls: Vec::new(),
+            wells: Vec::new(),
+            spawn: None,
+        };
+    }
+
+    pub fn load(path: &Path) -> Result<Arena, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        return Arena::parse(&text).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+
+    pub fn parse(text: &str) -> Result<Arena, String> {
+        let mut arena = Arena::empty();
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (shape, args) = match words.split_first() {
+                Some((shape, _)) if shape.starts_with('#') => continue,
+                Some((shape, args)) => (*shape, parse_numbers(line_number, args)?),
+                None => continue,
+            };
+            match (shape, args.as_slice()) {
+                ("spawn", &[x, y]) => arena.spawn = Some(Vec2::new(x, y)),
+                ("wall", &[x1, y1, x2, y2]) => arena.walls.push(Segment::from([x1, y1, x2, y2])),
+                ("polygon", coords) if coords.len() >= 6 && coords.len() % 2 == 0 => {
+                    let outline =
+                        Polygon::new(coords.chunks(2).map(|c| Vec2::new(c[0], c[1])).collect());
+                    arena.walls.extend(outline.edges());
+                }
+                ("circle", &[x, y, radius, sides])
+                    if sides.fract() == 0.0 && (3.0..=MAX_CIRCLE_SIDES).contains(&sides) =>
+                {
+                    let sides = sides as usize;
+                    let corner =
+                        |theta: f64| Vec2::new(x + radius * theta.cos(), y + radius * theta.sin());
+                    let outline = Polygon::new(
+                        (0..sides)
+                            .map(|i| corner(2.0 * PI * i as f64 / sides as f64))
+                            .collect(),
+                    );
+                    arena.walls.extend(outline.edges());
+                }
+                ("well", &[x, y, strength, horizon]) => {
+                    arena
+                        .wells
+                        .push(GravityWell::new(Vec2::new(x, y), strength, horizon));
+                }
+                _ => return Err(format!("line {}: can't understand {}", line_number, line)),
+            }
+        }
+        return Ok(arena);
+    }
+
+    pub fn walls(&self) -> &[Segment] {
+        return &self.walls;
+    }
+
+    pub fn wells(&self) -> &[GravityWell] {
+        return &self.wells;
+    }
+
+    // Whether something at p has fallen into a gravity well
+    pub fn swallows(&self, p: Vec2, bounds: &Bounds) -> bool {
+        return self.wells.iter().any(|well| well.swallows(p, bounds));
+    }
+
+    // Where the ship starts, if the level says
+    pub fn spawn(&self) -> Option<Vec2> {
+        return self.spawn;
+    }
+
+    // Whether a straight path between two points is clear of walls
+    pub fn clear_path(&self, from: Vec2, to: Vec2) -> bool {
+        let path = Segment::new(from, to);
+        return !self.walls.iter().any(|wall| lines_intersect(path, *wall));
+    }
+
+    // Picks a point with `pick` that can be reached in a straight line from
+    // `origin`, so that asteroids don't start out stuck outside the arena or
+    // inside an obstacle. Gives up and takes the last point after a few tries.
+    pub fn reachable_point<F: FnMut() -> Vec2>(&self, origin: Vec2, mut pick: F) -> Vec2 {
+        let mut point = pick();
+        for _ in 1..SPAWN_ATTEMPTS {
+            if self.clear_path(origin, point) {
+                break;
+            }
+            point = pick();
+        }
+        return point;
+    }
+
+    // The velocity of something with the given outline after it bounces off
+    // any walls it's touching and moving into.
+    pub fn deflect<I: Iterator<Item = Segment> + Clone>(
+        &self,
+        centre: Vec2,
+        velocity: Vec2,
+        edges: I,
+    ) -> Vec2 {
+        let mut velocity = velocity;
+        for wall in self.walls.iter() {
+            if !edges.clone().any(|edge| lines_intersect(edge, *wall)) {
+                continue;
+            }
+            let Some(normal) = wall.normal() else {
+                continue;
+            };
+            let side = (centre - wall.start).dot(normal);
+            let into_wall = velocity.dot(normal);
+            if side * into_wall < 0.0 {
+                velocity -= normal * (2.0 * into_wall);
+            }
+        }
+        return velocity;
+    }
+
+    pub fn draw(&self, color: Color, renderer: &mut dyn Renderer) {
+        for wall in self.walls.iter() {
+            renderer.line(color, 1.0, *wall);
+        }
+        for well in self.wells.iter() {
+            well.draw(color, renderer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn test_parse() {
+        let arena = Arena::parse(
+            "# A box with a pillar\n\
+             spawn 100 40\n\
+             \n\
+             wall 0 0 10 0\n\
+             polygon 90 90 110 90 110 110 90 110\n\
+             circle 100 100 50 8\n\
+             well 20 30 500 4\n",
+        )
+        .unwrap();
+        expect!(arena.spawn()).to(be_equal_to(Some(Vec2::new(100.0, 40.0))));
+        expect!(arena.walls().len()).to(be_equal_to(1 + 4 + 8));
+        expect!(arena.walls()[4]).to(be_equal_to(Segment::from([90.0, 110.0, 90.0, 90.0])));
+        expect!(arena.wells().to_vec()).to(be_equal_to(vec![GravityWell::new(
+            Vec2::new(20.0, 30.0),
+            500.0,
+            4.0,
+        )]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Arena::parse("wall 0 0 10").is_err());
+        assert!(Arena::parse("wall 0 0 10 x").is_err());
+        assert!(Arena::parse("polygon 0 0 10 10").is_err());
+        assert!(Arena::parse("circle 0 0 10 2").is_err());
+        assert!(Arena::parse("circle 0 0 10 3.7").is_err());
+        assert!(Arena::parse("circle 0 0 10 inf").is_err());
+        assert!(Arena::parse("circle 0 0 10 1e18").is_err());
+        assert!(Arena::parse("circle 0 0 10 NaN").is_err());
+        let error = Arena::parse("circle 0 0 10 1025").unwrap_err();
+        expect!(error).to(be_equal_to(
+            "line 1: can't understand circle 0 0 10 1025".to_string(),
+        ));
+        assert!(Arena::parse("circle 0 0 10 1024").is_ok());
+        assert!(Arena::parse("door 0 0 10 10").is_err());
+        assert!(Arena::parse("well 0 0 10").is_err());
+    }
+
+    #[test]
+    fn test_levels_load() {
+        for level in ["circle", "pillar", "corridors", "black_hole"] {
+            let path = format!("res/arenas/{}.txt", level);
+            let arena = Arena::load(Path::new(&path)).unwrap();
+            assert!(!arena.walls().is_empty() || !arena.wells().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_clear_path() {
+        let arena = Arena::parse("wall 50 0 50 100").unwrap();
+        assert!(arena.clear_path(Vec2::new(0.0, 50.0), Vec2::new(40.0, 50.0)));
+        assert!(!arena.clear_path(Vec2::new(0.0, 50.0), Vec2::new(60.0, 50.0)));
+    }
+
+    #[test]
+    fn test_deflect() {
+        let arena = Arena::parse("wall 50 0 50 100").unwrap();
+        let touching = [Segment::from([48.0, 50.0, 52.0, 50.0])];
+        // Heading into the wall from either side
+        let v = arena.deflect(
+            Vec2::new(49.0, 50.0),
+            Vec2::new(10.0, 5.0),
+            touching.into_iter(),
+        );
+        expect!(v).to(be_equal_to(Vec2::new(-10.0, 5.0)));
+        let v = arena.deflect(
+            Vec2::new(51.0, 50.0),
+            Vec2::new(-10.0, 5.0),
+            touching.into_iter(),
+        );
+        expect!(v).to(be_equal_to(Vec2::new(10.0, 5.0)));
+        // Already moving away
+        let v = arena.deflect(
+            Vec2::new(49.0, 50.0),
+            Vec2::new(-10.0, 5.0),
+            touching.into_iter(),
+        );
+        expect!(v).to(be_equal_to(Vec2::new(-10.0, 5.0)));
+        // Not touching
+        let v = arena.deflect(
+            Vec2::new(40.0, 50.0),
+            Vec2::new(10.0, 5.0),
+            [Segment::from([38.0, 50.0, 42.0, 50.0])].into_iter(),
+        );
+        expect!(v).to(be_equal_to(Vec2::new(10.0, 5.0)));
+    }
+}
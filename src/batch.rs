@@ -0,0 +1,319 @@
+use crate::actors::{Astroid, Controls, SPACESHIP_RADIUS};
+use crate::config::Config;
+use crate::geometry::Vec2;
+use crate::raycast::Mask;
+use crate::world::{Outcome, World};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::f64::consts::PI;
+use std::thread;
+
+// Runs many games at once for training and evaluating bots. Every world gets
+// its own stream of random numbers from the batch's seed, so results are the
+// same however many threads share the work.
+
+// Seconds per tick, the same as the game's default update rate
+pub const TICK: f64 = 1.0 / 120.0;
+
+// Each observation is the ship's position, velocity, the sine and cosine of
+// its heading and whether it can fire, followed by the offset, relative
+// velocity, radius and gap between outlines of each of the asteroids closest
+// to the ship's outline, nearest first. Missing asteroids are all zeroes.
+// Last come the distances to the nearest asteroid or wall along rays fanned
+// out evenly from the ship's nose, as a fraction of LIDAR_RANGE, or 1 if
+// there's nothing in range.
+pub const OBSERVED_ASTROIDS: usize = 8;
+const SHIP_VALUES: usize = 7;
+const ASTROID_VALUES: usize = 6;
+pub const LIDAR_RAYS: usize = 8;
+const LIDAR_RANGE: f64 = 200.0;
+const LIDAR_START: usize = SHIP_VALUES + OBSERVED_ASTROIDS * ASTROID_VALUES;
+pub const OBSERVATION_SIZE: usize = LIDAR_START + LIDAR_RAYS;
+
+const HIT_REWARD: f32 = 1.0;
+const NEAR_MISS_REWARD: f32 = 0.5;
+const WAVE_REWARD: f32 = 10.0;
+const DEATH_REWARD: f32 = -10.0;
+
+pub struct WorldBatch {
+    config: Config,
+    worlds: Vec<World>,
+    threads: usize,
+    observations: Vec<f32>,
+    rewards: Vec<f32>,
+    done: Vec<bool>,
+}
+
+// What came of one tick of every world, one entry per world in each array,
+// or OBSERVATION_SIZE entries per world for the observations.
+pub struct BatchStep<'a> {
+    pub observations: &'a [f32],
+    pub rewards: &'a [f32],
+    // Whether the ship was destroyed, in which case the world starts over
+    pub done: &'a [bool],
+}
+
+fn observe(world: &mut World, config: &Config, observation: &mut [f32]) {
+    let bounds = config.bounds();
+    let ship = world.spaceship();
+    let (position, velocity, heading) = (ship.position(), ship.velocity(), ship.heading());
+    let ready = if ship.ready_to_fire() { 1.0 } else { 0.0 };
+    let ship_values = [
+        position.x,
+        position.y,
+        velocity.x,
+        velocity.y,
+        heading.sin(),
+        heading.cos(),
+        ready,
+    ];
+    observation.fill(0.0);
+    for (value, &x) in observation.iter_mut().zip(ship_values.iter()) {
+        *value = x as f32;
+    }
+    // The gap between outlines is no more than the distance between centres,
+    // and no less than that without both radii, so only asteroids that could
+    // be among the nearest have their outlines measured
+    let centres = |astroid: &Astroid| bounds.delta(position, astroid.obj().position()).length();
+    let mut closest_centres = [f64::INFINITY; OBSERVED_ASTROIDS];
+    for astroid in world.astroids().iter() {
+        let distance = centres(astroid);
+        if distance < closest_centres[OBSERVED_ASTROIDS - 1] {
+            closest_centres[OBSERVED_ASTROIDS - 1] = distance;
+            closest_centres.sort_unstable_by(f64::total_cmp);
+        }
+    }
+    let mut nearest = [(f64::INFINITY, usize::MAX); OBSERVED_ASTROIDS];
+    for (i, astroid) in world.astroids().iter().enumerate() {
+        let furthest = nearest[OBSERVED_ASTROIDS - 1]
+            .0
+            .min(closest_centres[OBSERVED_ASTROIDS - 1]);
+        if centres(astroid) - astroid.radius() - SPACESHIP_RADIUS > furthest {
+            continue;
+        }
+        let distance = ship.distance_to(astroid, &bounds);
+        if distance < nearest[OBSERVED_ASTROIDS - 1].0 {
+            nearest[OBSERVED_ASTROIDS - 1] = (distance, i);
+            nearest.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        }
+    }
+    let slots = observation[SHIP_VALUES..].chunks_mut(ASTROID_VALUES);
+    for (slot, &(gap, i)) in slots
+        .zip(nearest.iter())
+        .filter(|(_, &(_, i))| i != usize::MAX)
+    {
+        let astroid = &world.astroids()[i];
+        let d = bounds.delta(position, astroid.obj().position());
+        let v = astroid.obj().velocity() - velocity;
+        let values = [d.x, d.y, v.x, v.y, astroid.radius(), gap];
+        for (value, x) in slot.iter_mut().zip(values) {
+            *value = x as f32;
+        }
+    }
+    let rays = observation[LIDAR_START..].iter_mut().enumerate();
+    for (i, value) in rays {
+        let direction = Vec2::from_heading(heading + 2.0 * PI * i as f64 / LIDAR_RAYS as f64, 1.0);
+        let mask = Mask::ASTROIDS | Mask::WALLS;
+        let hit = world.raycast(position, direction, LIDAR_RANGE, mask, config);
+        *value = hit.map_or(1.0, |hit| (hit.distance / LIDAR_RANGE) as f32);
+    }
+}
+
+// Steps one world, starting it over if the ship was destroyed
+fn step_world(world: &mut World, controls: Controls, config: &Config) -> (f32, bool) {
+    let (hits, near_misses) = (world.hits(), world.score().near_misses());
+    world.steer(controls);
+    let outcome = world.step(TICK, config);
+    let reward = (world.hits() - hits) as f32 * HIT_REWARD
+        + (world.score().near_misses() - near_misses) as f32 * NEAR_MISS_REWARD;
+    return match outcome {
+        Outcome::Playing => (reward, false),
+        Outcome::WaveCleared => {
+            world.next_wave(config);
+            (reward + WAVE_REWARD, false)
+        }
+        Outcome::ShipDestroyed => {
+            world.start_wave(1, config);
+            (reward + DEATH_REWARD, true)
+        }
+    };
+}
+
+impl WorldBatch {
+    pub fn new(config: Config, worlds: usize, threads: usize, seed: u64) -> WorldBatch {
+        let mut seeds = StdRng::seed_from_u64(seed);
+        let mut worlds: Vec<World> = (0..worlds)
+            .map(|_| World::new(1, &config, StdRng::seed_from_u64(seeds.next_u64())))
+            .collect();
+        let mut observations = vec![0.0; worlds.len() * OBSERVATION_SIZE];
+        for (world, observation) in worlds
+            .iter_mut()
+            .zip(observations.chunks_mut(OBSERVATION_SIZE))
+        {
+            observe(world, &config, observation);
+        }
+        return WorldBatch {
+            rewards: vec![0.0; worlds.len()],
+            done: vec![false; worlds.len()],
+            config: config,
+            worlds: worlds,
+            threads: threads.max(1),
+            observations: observations,
+        };
+    }
+
+    // What each world looks like before the first step
+    pub fn observations(&self) -> &[f32] {
+        return &self.observations;
+    }
+
+    // Steps every world by one tick with its own controls, spreading the
+    // worlds over the batch's threads.
+    pub fn step_all(&mut self, inputs: &[Controls]) -> BatchStep<'_> {
+        assert_eq!(
+            inputs.len(),
+            self.worlds.len(),
+            "one set of controls per world"
+        );
+        let per_thread = self.worlds.len().div_ceil(self.threads).max(1);
+        let config = &self.config;
+        let chunks = self
+            .worlds
+            .chunks_mut(per_thread)
+            .zip(inputs.chunks(per_thread))
+            .zip(self.observations.chunks_mut(per_thread * OBSERVATION_SIZE))
+            .zip(self.rewards.chunks_mut(per_thread))
+            .zip(self.done.chunks_mut(per_thread));
+        thread::scope(|scope| {
+            for ((((worlds, inputs), observations), rewards), done) in chunks {
+                scope.spawn(move || {
+                    let observations = observations.chunks_mut(OBSERVATION_SIZE);
+                    for (i, (world, observation)) in worlds.iter_mut().zip(observations).enumerate()
+                    {
+                        (rewards[i], done[i]) = step_world(world, inputs[i], config);
+                        observe(world, config, observation);
+                    }
+                });
+            }
+        });
+        return BatchStep {
+            observations: &self.observations,
+            rewards: &self.rewards,
+            done: &self.done,
+        };
+    }
+}
+
+// A simple bot to measure others against: it turns toward the nearest
+// asteroid and fires once it's lined up.
+pub fn aim_at_nearest(observation: &[f32]) -> Controls {
+    let nearest = &observation[SHIP_VALUES..SHIP_VALUES + ASTROID_VALUES];
+    if nearest[4] == 0.0 {
+        return Controls::default();
+    }
+    let heading = (observation[4] as f64).atan2(observation[5] as f64);
+    let target = Vec2::new(nearest[0] as f64, nearest[1] as f64).heading();
+    let off_target = (target - heading + PI).rem_euclid(2.0 * PI) - PI;
+    return Controls {
+        thrust: 0.0,
+        turn: off_target.signum(),
+        fire: off_target.abs() < 0.1,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+
+    fn run(threads: usize, ticks: usize) -> (Vec<f32>, Vec<f32>, Vec<bool>) {
+        let mut batch = WorldBatch::new(Config::new(), 5, threads, 38);
+        let mut rewards = vec![0.0; 5];
+        let mut done = vec![false; 5];
+        for _ in 0..ticks {
+            let inputs: Vec<Controls> = batch
+                .observations()
+                .chunks(OBSERVATION_SIZE)
+                .map(aim_at_nearest)
+                .collect();
+            let step = batch.step_all(&inputs);
+            for i in 0..5 {
+                rewards[i] += step.rewards[i];
+                done[i] |= step.done[i];
+            }
+        }
+        return (batch.observations().to_vec(), rewards, done);
+    }
+
+    #[test]
+    fn test_worlds_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<World>();
+    }
+
+    #[test]
+    fn test_step_all() {
+        let (observations, rewards, _) = run(2, 600);
+        expect!(observations.len()).to(be_equal_to(5 * OBSERVATION_SIZE));
+        // Each world got its own asteroids
+        let nearest = |world: usize| observations[world * OBSERVATION_SIZE + SHIP_VALUES];
+        assert!(nearest(0) != nearest(1));
+        // Lining up shots for five seconds hits something
+        assert!(rewards.iter().any(|&reward| reward > 0.0));
+    }
+
+    #[test]
+    fn test_threads_dont_change_results() {
+        expect!(run(1, 300)).to(be_equal_to(run(4, 300)));
+        expect!(run(3, 300)).to(be_equal_to(run(8, 300)));
+    }
+
+    #[test]
+    fn test_observe() {
+        let batch = WorldBatch::new(Config::new(), 1, 1, 0);
+        let observation = batch.observations();
+        // The ship starts in the middle pointing up and ready to fire
+        expect!(&observation[..SHIP_VALUES])
+            .to(be_equal_to(&[100.0, 100.0, 0.0, 0.0, 0.0, 1.0, 1.0][..]));
+        // The first wave has fewer asteroids than there are slots
+        let astroids = batch.worlds[0].astroids().len();
+        assert!(astroids < OBSERVED_ASTROIDS);
+        let slot = |i: usize| {
+            let start = SHIP_VALUES + i * ASTROID_VALUES;
+            &observation[start..start + ASTROID_VALUES]
+        };
+        // Sorted by the gap between the outlines
+        for i in 1..astroids {
+            assert!(slot(i - 1)[5] <= slot(i)[5]);
+            assert!(slot(i)[5] > 0.0);
+        }
+        assert!(slot(astroids).iter().all(|&x| x == 0.0));
+        // Every ray reports a distance within range
+        let lidar = &observation[LIDAR_START..];
+        expect!(lidar.len()).to(be_equal_to(LIDAR_RAYS));
+        assert!(lidar.iter().all(|&x| x > 0.0 && x <= 1.0));
+    }
+
+    #[test]
+    fn test_observe_crowded() {
+        // More asteroids than slots, so some are never measured, but the
+        // nearest are the same as measuring every one
+        let config = Config::new();
+        let bounds = config.bounds();
+        let mut observation = [0.0; OBSERVATION_SIZE];
+        for seed in 0..10 {
+            let mut world = World::new(30, &config, StdRng::seed_from_u64(seed));
+            observe(&mut world, &config, &mut observation);
+            let ship = world.spaceship();
+            let mut gaps: Vec<f32> = world
+                .astroids()
+                .iter()
+                .map(|astroid| ship.distance_to(astroid, &bounds) as f32)
+                .collect();
+            gaps.sort_by(f32::total_cmp);
+            for (i, &gap) in gaps.iter().take(OBSERVED_ASTROIDS).enumerate() {
+                expect!(observation[SHIP_VALUES + i * ASTROID_VALUES + 5]).to(be_equal_to(gap));
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::geometry::Vec2;
+
+pub fn wrapped_add(a: f64, b: f64, bound: f64) -> f64 {
+    (a + b + bound) % bound
+}
+
+// The shortest displacement from a to b when positions wrap at bound.
+pub fn wrapped_delta(a: f64, b: f64, bound: f64) -> f64 {
+    let d = (b - a) % bound;
+    if d > bound / 2.0 {
+        return d - bound;
+    } else if d < -bound / 2.0 {
+        return d + bound;
+    }
+    return d;
+}
+
+// What happens to things that reach the edge of the arena
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Boundary {
+    // They come back in on the opposite side
+    Wrap,
+    // They bounce off it
+    Bounce,
+    // They're destroyed
+    Lethal,
+    // They leave for good, and new asteroids stream in to replace them
+    Open,
+}
+
+impl Boundary {
+    pub fn from_name(name: &str) -> Option<Boundary> {
+        return match name {
+            "wrap" => Some(Boundary::Wrap),
+            "bounce" => Some(Boundary::Bounce),
+            "lethal" => Some(Boundary::Lethal),
+            "open" => Some(Boundary::Open),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bounds {
+    pub width: f64,
+    pub height: f64,
+    pub boundary: Boundary,
+}
+
+// The same arena as the default config, for tests
+#[cfg(test)]
+pub const TEST_BOUNDS: Bounds = Bounds {
+    width: 200.0,
+    height: 200.0,
+    boundary: Boundary::Wrap,
+};
+
+impl Bounds {
+    pub fn new(width: f64, height: f64, boundary: Boundary) -> Bounds {
+        return Bounds {
+            width: width,
+            height: height,
+            boundary: boundary,
+        };
+    }
+
+    pub fn contains(&self, p: Vec2) -> bool {
+        return (0.0..self.width).contains(&p.x) && (0.0..self.height).contains(&p.y);
+    }
+
+    // Moves something at `position` by `d`. Returns its new position and
+    // velocity, or None if it left the arena for good.
+    pub fn advance(&self, position: Vec2, velocity: Vec2, d: Vec2) -> Option<(Vec2, Vec2)> {
+        match self.boundary {
+            Boundary::Wrap => {
+                let p = Vec2::new(
+                    wrapped_add(position.x, d.x, self.width),
+                    wrapped_add(position.y, d.y, self.height),
+                );
+                return Some((p, velocity));
+            }
+            Boundary::Bounce => {
+                let (x, vx) = reflect(position.x + d.x, velocity.x, self.width);
+                let (y, vy) = reflect(position.y + d.y, velocity.y, self.height);
+                return Some((Vec2::new(x, y), Vec2::new(vx, vy)));
+            }
+            Boundary::Lethal | Boundary::Open => {
+                let p = position + d;
+                if self.contains(p) {
+                    return Some((p, velocity));
+                }
+                return None;
+            }
+        }
+    }
+
+    // Moves a point by `d` without changing how it's travelling
+    pub fn translate(&self, position: Vec2, d: Vec2) -> Vec2 {
+        if self.boundary == Boundary::Wrap {
+            return Vec2::new(
+                wrapped_add(position.x, d.x, self.width),
+                wrapped_add(position.y, d.y, self.height),
+            );
+        }
+        return position + d;
+    }
+
+    // The shortest displacement from one point to another
+    pub fn delta(&self, from: Vec2, to: Vec2) -> Vec2 {
+        if self.boundary == Boundary::Wrap {
+            return Vec2::new(
+                wrapped_delta(from.x, to.x, self.width),
+                wrapped_delta(from.y, to.y, self.height),
+            );
+        }
+        return to - from;
+    }
+
+    // Offsets at which something within `reach` of the edge of the screen
+    // shows up again on the opposite side. The first offset is always the
+    // object itself, and it's the only one unless the arena wraps.
+    pub fn ghosts(&self, position: Vec2, reach: f64) -> impl Iterator<Item = Vec2> + Clone {
+        let wraps = self.boundary == Boundary::Wrap;
+        let offsets = move |p: f64, max: f64| {
+            [
+                Some(0.0),
+                (wraps && p < reach).then_some(max),
+                (wraps && p > max - reach).then_some(-max),
+            ]
+            .into_iter()
+            .flatten()
+        };
+        let xs = offsets(position.x, self.width);
+        let ys = offsets(position.y, self.height);
+        return ys.flat_map(move |dy| xs.clone().map(move |dx| Vec2::new(dx, dy)));
+    }
+}
+
+fn reflect(p: f64, v: f64, max: f64) -> (f64, f64) {
+    if p < 0.0 {
+        return (-p, v.abs());
+    } else if p >= max {
+        return (2.0 * max - p, -v.abs());
+    }
+    return (p, v);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+
+    fn bounds(boundary: Boundary) -> Bounds {
+        return Bounds::new(200.0, 200.0, boundary);
+    }
+
+    #[test]
+    fn test_wrapped_add() {
+        assert_eq!(wrapped_add(1.0, 1.0, 10.0), 2.0);
+        assert_eq!(wrapped_add(1.0, 10.0, 10.0), 1.0);
+        assert_eq!(wrapped_add(1.0, -5.0, 10.0), 6.0);
+    }
+
+    #[test]
+    fn test_wrapped_delta() {
+        assert_eq!(wrapped_delta(1.0, 3.0, 10.0), 2.0);
+        assert_eq!(wrapped_delta(1.0, 9.0, 10.0), -2.0);
+        assert_eq!(wrapped_delta(9.0, 1.0, 10.0), 2.0);
+    }
+
+    #[test]
+    fn test_ghost_offsets() {
+        let offsets = |x, y| {
+            bounds(Boundary::Wrap)
+                .ghosts(Vec2::new(x, y), 10.0)
+                .collect::<Vec<_>>()
+        };
+        expect!(offsets(100.0, 100.0)).to(be_equal_to(vec![Vec2::zero()]));
+        expect!(offsets(5.0, 100.0)).to(be_equal_to(vec![Vec2::zero(), Vec2::new(200.0, 0.0)]));
+        expect!(offsets(195.0, 195.0)).to(be_equal_to(vec![
+            Vec2::zero(),
+            Vec2::new(-200.0, 0.0),
+            Vec2::new(0.0, -200.0),
+            Vec2::new(-200.0, -200.0),
+        ]));
+        let offsets = bounds(Boundary::Bounce).ghosts(Vec2::new(195.0, 195.0), 10.0);
+        expect!(offsets.collect::<Vec<_>>()).to(be_equal_to(vec![Vec2::zero()]));
+    }
+
+    #[test]
+    fn test_advance() {
+        let p = Vec2::new(195.0, 100.0);
+        let v = Vec2::new(10.0, 5.0);
+        let d = Vec2::new(10.0, 5.0);
+        expect!(bounds(Boundary::Wrap).advance(p, v, d))
+            .to(be_equal_to(Some((Vec2::new(5.0, 105.0), v))));
+        expect!(bounds(Boundary::Bounce).advance(p, v, d)).to(be_equal_to(Some((
+            Vec2::new(195.0, 105.0),
+            Vec2::new(-10.0, 5.0),
+        ))));
+        expect!(bounds(Boundary::Lethal).advance(p, v, d)).to(be_equal_to(None));
+        expect!(bounds(Boundary::Open).advance(p, v, d)).to(be_equal_to(None));
+        let inside = Vec2::new(100.0, 100.0);
+        for boundary in [Boundary::Bounce, Boundary::Lethal, Boundary::Open] {
+            expect!(bounds(boundary).advance(inside, v, d))
+                .to(be_equal_to(Some((Vec2::new(110.0, 105.0), v))));
+        }
+    }
+
+    #[test]
+    fn test_bounce_off_top_left() {
+        let p = Vec2::new(2.0, 1.0);
+        let v = Vec2::new(-10.0, -10.0);
+        expect!(bounds(Boundary::Bounce).advance(p, v, Vec2::new(-4.0, -4.0))).to(be_equal_to(
+            Some((Vec2::new(2.0, 3.0), Vec2::new(10.0, 10.0))),
+        ));
+    }
+}
@@ -0,0 +1,350 @@
+use crate::geometry::{bounding_box, Polygon, Segment, Vec2};
+use crate::intersect::intersection_time;
+
+// Polygon overlap with the separating axis test. Two convex polygons overlap
+// unless there's a line, parallel to one of their edges, that they project
+// onto without overlapping. Concave polygons are split into convex parts
+// first. Unlike comparing edges, this also catches one polygon sitting
+// entirely inside another.
+
+// How two shapes overlap
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contact {
+    // How far the second shape has to move along the normal to stop overlapping
+    pub depth: f64,
+    // Unit vector pointing from the first shape toward the second
+    pub normal: Vec2,
+    // Corners of the region where the shapes overlap, in world coordinates
+    pub points: Vec<Vec2>,
+}
+
+fn cross_at(points: &[Vec2], i: usize) -> f64 {
+    let n = points.len();
+    let (a, b, c) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+    return (b - a).cross(c - b);
+}
+
+// Whether p is inside the triangle or on its edges
+fn in_triangle(p: Vec2, [a, b, c]: [Vec2; 3]) -> bool {
+    let d1 = (b - a).cross(p - a);
+    let d2 = (c - b).cross(p - b);
+    let d3 = (a - c).cross(p - c);
+    let negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    return !(negative && positive);
+}
+
+// Splits a simple polygon into triangles by clipping off one ear, a corner
+// with no other corners inside it, at a time.
+pub fn triangulate(polygon: &Polygon) -> Vec<[Vec2; 3]> {
+    let mut remaining: Vec<Vec2> = polygon.points().to_vec();
+    if polygon.signed_area() < 0.0 {
+        remaining.reverse();
+    }
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let triangle = [
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            ];
+            cross_at(&remaining, i) >= 0.0
+                && !remaining
+                    .iter()
+                    .filter(|p| !triangle.contains(p))
+                    .any(|&p| in_triangle(p, triangle))
+        });
+        // Only a self-intersecting outline has no ears. Cut anywhere rather
+        // than give up.
+        let i = ear.unwrap_or(0);
+        let triangle = [
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        ];
+        if cross_at(&remaining, i) != 0.0 {
+            triangles.push(triangle);
+        }
+        remaining.remove(i);
+    }
+    if remaining.len() == 3 && cross_at(&remaining, 1) != 0.0 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    return triangles;
+}
+
+// Convex pieces that cover the polygon. Convex polygons are left whole.
+pub fn convex_parts(polygon: &Polygon) -> Vec<Polygon> {
+    if polygon.is_convex() {
+        return vec![polygon.clone()];
+    }
+    return triangulate(polygon)
+        .iter()
+        .map(|triangle| Polygon::new(triangle.to_vec()))
+        .collect();
+}
+
+// The range covered by the polygon along the axis
+fn project(points: &[Vec2], offset: Vec2, axis: Vec2) -> (f64, f64) {
+    return points
+        .iter()
+        .map(|&p| (p + offset).dot(axis))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+            (min.min(x), max.max(x))
+        });
+}
+
+fn edge_normals(points: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    return points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .filter_map(|(&p1, &p2)| {
+            let along = p2 - p1;
+            let length = along.length();
+            if length == 0.0 {
+                return None;
+            }
+            return Some(Vec2::new(-along.y, along.x) / length);
+        });
+}
+
+// Whether p is inside the convex polygon or on its edges
+fn in_convex(p: Vec2, points: &[Vec2], offset: Vec2) -> bool {
+    let (mut left, mut right) = (false, false);
+    for (&p1, &p2) in points.iter().zip(points.iter().cycle().skip(1)) {
+        let cross = (p2 - p1).cross(p - (p1 + offset));
+        left |= cross > 0.0;
+        right |= cross < 0.0;
+    }
+    return !(left && right);
+}
+
+// Corners of the overlap of two convex polygons: the corners of each that are
+// inside the other, and the points where their edges cross.
+fn overlap_corners(a: &[Vec2], a_offset: Vec2, b: &[Vec2], b_offset: Vec2) -> Vec<Vec2> {
+    let mut corners: Vec<Vec2> = a
+        .iter()
+        .map(|&p| p + a_offset)
+        .filter(|&p| in_convex(p, b, b_offset))
+        .chain(
+            b.iter()
+                .map(|&p| p + b_offset)
+                .filter(|&p| in_convex(p, a, a_offset)),
+        )
+        .collect();
+    let edges = |points: &[Vec2], offset: Vec2| -> Vec<Segment> {
+        return points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(&p1, &p2)| Segment::new(p1 + offset, p2 + offset))
+            .collect();
+    };
+    for a_edge in edges(a, a_offset) {
+        for b_edge in edges(b, b_offset) {
+            if let Some(t) = intersection_time(a_edge, b_edge) {
+                corners.push(a_edge.at(t));
+            }
+        }
+    }
+    corners.dedup();
+    return corners;
+}
+
+// The overlap of two convex polygons, given by their corners, each moved by
+// its offset
+pub fn convex_contact(a: &[Vec2], a_offset: Vec2, b: &[Vec2], b_offset: Vec2) -> Option<Contact> {
+    // Boxes that don't overlap rule out the polygons without trying every axis
+    let ((a_low, a_high), (b_low, b_high)) = (bounding_box(a), bounding_box(b));
+    let (a_low, a_high) = (a_low + a_offset, a_high + a_offset);
+    let (b_low, b_high) = (b_low + b_offset, b_high + b_offset);
+    if a_high.x <= b_low.x || b_high.x <= a_low.x || a_high.y <= b_low.y || b_high.y <= a_low.y {
+        return None;
+    }
+    let mut best: Option<(f64, Vec2)> = None;
+    for axis in edge_normals(a).chain(edge_normals(b)) {
+        let (a_min, a_max) = project(a, a_offset, axis);
+        let (b_min, b_max) = project(b, b_offset, axis);
+        // Enough to push b out past either side of a, which also covers b
+        // being inside a.
+        let depth = (a_max - b_min).min(b_max - a_min);
+        if depth <= 0.0 {
+            return None;
+        }
+        if best.is_none_or(|(best_depth, _)| depth < best_depth) {
+            let normal = if a_max - b_min <= b_max - a_min {
+                axis
+            } else {
+                -axis
+            };
+            best = Some((depth, normal));
+        }
+    }
+    let (depth, normal) = best?;
+    return Some(Contact {
+        depth: depth,
+        normal: normal,
+        points: overlap_corners(a, a_offset, b, b_offset),
+    });
+}
+
+// The overlap of two shapes made of convex parts. The deepest overlap
+// between any two parts gives the depth and normal, and the points come
+// from all of them.
+pub fn contact(a: &[Polygon], a_offset: Vec2, b: &[Polygon], b_offset: Vec2) -> Option<Contact> {
+    let mut deepest: Option<Contact> = None;
+    for a_part in a.iter() {
+        for b_part in b.iter() {
+            let Some(contact) =
+                convex_contact(a_part.points(), a_offset, b_part.points(), b_offset)
+            else {
+                continue;
+            };
+            deepest = Some(match deepest {
+                None => contact,
+                Some(mut deepest) => {
+                    if contact.depth > deepest.depth {
+                        deepest.depth = contact.depth;
+                        deepest.normal = contact.normal;
+                    }
+                    deepest.points.extend(contact.points);
+                    deepest
+                }
+            });
+        }
+    }
+    return deepest;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+
+    fn square(x: f64, y: f64, half: f64) -> Polygon {
+        return Polygon::square(half).translate(Vec2::new(x, y));
+    }
+
+    // An L with its notch in the top right
+    fn ell() -> Polygon {
+        return Polygon::new(vec![
+            Vec2::zero(),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(4.0, 2.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(2.0, 4.0),
+            Vec2::new(0.0, 4.0),
+        ]);
+    }
+
+    fn area(triangles: &[[Vec2; 3]]) -> f64 {
+        return triangles
+            .iter()
+            .map(|t| Polygon::new(t.to_vec()).area())
+            .sum();
+    }
+
+    #[test]
+    fn test_overlapping_squares() {
+        let a = square(0.0, 0.0, 5.0);
+        let b = square(8.0, 1.0, 5.0);
+        let contact = convex_contact(a.points(), Vec2::zero(), b.points(), Vec2::zero()).unwrap();
+        expect!(contact.depth).to(be_close_to(2.0));
+        expect!(contact.normal).to(be_equal_to(Vec2::new(1.0, 0.0)));
+        // The overlap is the strip from x = 3 to 5 and y = -4 to 5
+        let mut xs: Vec<f64> = contact.points.iter().map(|p| p.x).collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expect!(xs).to(be_equal_to(vec![3.0, 3.0, 5.0, 5.0]));
+        // From the other side the normal flips
+        let contact = convex_contact(b.points(), Vec2::zero(), a.points(), Vec2::zero()).unwrap();
+        expect!(contact.normal).to(be_equal_to(Vec2::new(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn test_apart_and_touching() {
+        let a = square(0.0, 0.0, 5.0);
+        expect!(convex_contact(
+            a.points(),
+            Vec2::zero(),
+            square(11.0, 0.0, 5.0).points(),
+            Vec2::zero()
+        ))
+        .to(be_none());
+        expect!(convex_contact(
+            a.points(),
+            Vec2::zero(),
+            square(10.0, 0.0, 5.0).points(),
+            Vec2::zero()
+        ))
+        .to(be_none());
+        // Offsets move the shapes
+        let b = square(0.0, 0.0, 5.0);
+        expect!(convex_contact(
+            a.points(),
+            Vec2::zero(),
+            b.points(),
+            Vec2::new(11.0, 0.0)
+        ))
+        .to(be_none());
+        assert!(convex_contact(
+            a.points(),
+            Vec2::new(3.0, 0.0),
+            b.points(),
+            Vec2::new(11.0, 0.0)
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn test_contained() {
+        let big = square(0.0, 0.0, 20.0);
+        let small = square(2.0, 0.0, 3.0);
+        let contact =
+            convex_contact(big.points(), Vec2::zero(), small.points(), Vec2::zero()).unwrap();
+        // Quickest way out is through the nearest side
+        expect!(contact.depth).to(be_close_to(21.0));
+        expect!(contact.normal).to(be_equal_to(Vec2::new(1.0, 0.0)));
+        expect!(contact.points.len()).to(be_equal_to(4));
+        assert!(convex_contact(small.points(), Vec2::zero(), big.points(), Vec2::zero()).is_some());
+    }
+
+    #[test]
+    fn test_triangulate() {
+        let triangles = triangulate(&ell());
+        expect!(triangles.len()).to(be_equal_to(4));
+        expect!(area(&triangles)).to(be_close_to(12.0));
+        // Either winding works
+        let mut backwards = ell().points().to_vec();
+        backwards.reverse();
+        let backwards = Polygon::new(backwards);
+        expect!(area(&triangulate(&backwards))).to(be_close_to(12.0));
+        // Repeated and collinear points don't add slivers
+        let mut doubled = ell().points().to_vec();
+        doubled.insert(1, Vec2::new(2.0, 0.0));
+        doubled.insert(1, Vec2::zero());
+        let doubled = Polygon::new(doubled);
+        expect!(area(&triangulate(&doubled))).to(be_close_to(12.0));
+    }
+
+    #[test]
+    fn test_convex_parts() {
+        expect!(convex_parts(&square(0.0, 0.0, 1.0)).len()).to(be_equal_to(1));
+        assert!(square(0.0, 0.0, 1.0).is_convex());
+        assert!(!ell().is_convex());
+        expect!(convex_parts(&ell()).len()).to(be_equal_to(4));
+    }
+
+    #[test]
+    fn test_concave_contact() {
+        let ell = convex_parts(&ell());
+        // Sitting in the notch without touching
+        let notch = vec![square(3.2, 3.2, 0.5)];
+        expect!(contact(&ell, Vec2::zero(), &notch, Vec2::zero())).to(be_none());
+        // Poking into the arm below the notch
+        let poking = vec![square(3.0, 2.3, 0.5)];
+        let c = contact(&ell, Vec2::zero(), &poking, Vec2::zero()).unwrap();
+        expect!(c.depth).to(be_close_to(0.2));
+        expect!(c.normal).to(be_equal_to(Vec2::new(0.0, 1.0)));
+    }
+}
@@ -1,10 +1,196 @@
-use std::path::Path;
+use crate::actors::Weapon;
+use crate::arena::Arena;
+use crate::bounds::{Boundary, Bounds};
+use crate::handling::{Handling, HandlingModel, Tuning};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-pub struct Config;
+// What to do when the game starts
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    // Open a window and play
+    Play,
+    // Run many worlds at once without a window, with a simple bot at the
+    // controls, and report how it did
+    Batch {
+        worlds: usize,
+        ticks: usize,
+        seed: u64,
+    },
+    // Play a seeded game without a window for some ticks, and save what the
+    // screen would show as a PNG
+    RenderFrame {
+        tick: usize,
+        out: String,
+        seed: u64,
+    },
+    // Play a replay back without a window, and save it as a GIF or a PNG
+    // for each frame
+    Export {
+        replay: String,
+        out: String,
+        fps: u32,
+        scale: f64,
+    },
+}
+
+#[derive(Clone)]
+pub struct Config {
+    command: Command,
+    boundary: Boundary,
+    arena: Arena,
+    handling: HandlingModel,
+    // Changes to how the handling preset flies
+    tuning: Tuning,
+    near_miss: f64,
+    // Whether asteroids bounce off each other
+    astroid_collisions: bool,
+    // Where to save a replay of each game played, numbered by game
+    record: Option<String>,
+}
+
+fn parse_number<T: FromStr>(option: &str, value: String) -> Result<T, String> {
+    return value
+        .parse()
+        .map_err(|_| format!("{} needs a number, not {}", option, value));
+}
+
+// A number that can't be negative, like a speed, though it can be infinite
+fn parse_amount(option: &str, value: String) -> Result<Option<f64>, String> {
+    let amount: f64 = parse_number(option, value)?;
+    if amount.is_nan() || amount < 0.0 {
+        return Err(format!("{} can't be less than 0", option));
+    }
+    return Ok(Some(amount));
+}
 
 impl Config {
     pub fn new() -> Config {
-        return Config;
+        return Config {
+            command: Command::Play,
+            boundary: Boundary::Wrap,
+            arena: Arena::empty(),
+            handling: HandlingModel::Newtonian,
+            tuning: Tuning::default(),
+            near_miss: 15.0,
+            astroid_collisions: false,
+            record: None,
+        };
+    }
+
+    // Reads settings given on the command line, e.g. `--boundary bounce`, or
+    // `batch --worlds 64` or `render-frame --tick 60` to run without a window
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Config, String> {
+        let mut config = Config::new();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--boundary" => {
+                    let name = value()?;
+                    config.boundary =
+                        Boundary::from_name(&name).ok_or(format!("unknown boundary: {}", name))?;
+                }
+                "--handling" => {
+                    let name = value()?;
+                    config.handling = HandlingModel::from_name(&name)
+                        .ok_or(format!("unknown handling: {}", name))?;
+                }
+                "--thrust" => config.tuning.thrust = parse_amount(&arg, value()?)?,
+                "--drag" => config.tuning.drag = parse_amount(&arg, value()?)?,
+                "--max-speed" => config.tuning.max_speed = parse_amount(&arg, value()?)?,
+                "--turn-rate" => config.tuning.turn_rate = parse_amount(&arg, value()?)?,
+                "--angular-accel" => config.tuning.angular_accel = parse_amount(&arg, value()?)?,
+                "--arena" => config.arena = Arena::load(Path::new(&value()?))?,
+                "--near-miss" => config.near_miss = parse_number(&arg, value()?)?,
+                "--astroid-collisions" => config.astroid_collisions = true,
+                "--record" => config.record = Some(value()?),
+                "batch" => {
+                    config.command = Command::Batch {
+                        worlds: 16,
+                        ticks: 3600,
+                        seed: 0,
+                    }
+                }
+                "--worlds" | "--ticks" => {
+                    let value = value()?;
+                    let Command::Batch { worlds, ticks, .. } = &mut config.command else {
+                        return Err(format!("{} only works with batch", arg));
+                    };
+                    match arg.as_str() {
+                        "--worlds" => *worlds = parse_number(&arg, value)?,
+                        _ => *ticks = parse_number(&arg, value)?,
+                    }
+                }
+                "render-frame" => {
+                    config.command = Command::RenderFrame {
+                        tick: 0,
+                        out: "frame.png".to_string(),
+                        seed: 0,
+                    }
+                }
+                "--tick" => {
+                    let value = value()?;
+                    let Command::RenderFrame { tick, .. } = &mut config.command else {
+                        return Err(format!("{} only works with render-frame", arg));
+                    };
+                    *tick = parse_number(&arg, value)?;
+                }
+                "export" => {
+                    config.command = Command::Export {
+                        replay: value()?,
+                        out: "replay.gif".to_string(),
+                        fps: 25,
+                        scale: 1.0,
+                    }
+                }
+                "--fps" | "--scale" => {
+                    let value = value()?;
+                    let Command::Export { fps, scale, .. } = &mut config.command else {
+                        return Err(format!("{} only works with export", arg));
+                    };
+                    match arg.as_str() {
+                        "--fps" => *fps = parse_number(&arg, value)?,
+                        _ => *scale = parse_number(&arg, value)?,
+                    }
+                }
+                "--out" => {
+                    let value = value()?;
+                    match &mut config.command {
+                        Command::RenderFrame { out, .. } | Command::Export { out, .. } => {
+                            *out = value
+                        }
+                        _ => return Err(format!("{} only works with render-frame or export", arg)),
+                    }
+                }
+                "--seed" => {
+                    let value = value()?;
+                    match &mut config.command {
+                        Command::Batch { seed, .. } | Command::RenderFrame { seed, .. } => {
+                            *seed = parse_number(&arg, value)?
+                        }
+                        _ => return Err(format!("{} only works with batch or render-frame", arg)),
+                    }
+                }
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+        return Ok(config);
+    }
+
+    pub fn command(&self) -> Command {
+        return self.command.clone();
+    }
+
+    // Where to save the replay of the `game`th game played, e.g. game-2.txt
+    // for `--record game.txt`
+    pub fn record_path(&self, game: usize) -> Option<PathBuf> {
+        let path = Path::new(self.record.as_ref()?);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(extension) => format!("{}-{}.{}", stem, game, extension.to_string_lossy()),
+            None => format!("{}-{}", stem, game),
+        };
+        return Some(path.with_file_name(name));
     }
 
     pub fn width(&self) -> f64 {
@@ -15,10 +201,100 @@ impl Config {
         return 200.0;
     }
 
+    pub fn boundary(&self) -> Boundary {
+        return self.boundary;
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        return Bounds::new(self.width(), self.height(), self.boundary());
+    }
+
+    pub fn arena(&self) -> &Arena {
+        return &self.arena;
+    }
+
+    // The handling preset, with any of its values given on the command line
+    // in their place
+    pub fn handling(&self) -> Handling {
+        let preset = match self.handling {
+            HandlingModel::Newtonian => Handling {
+                model: HandlingModel::Newtonian,
+                thrust: 100.0,
+                drag: 0.0,
+                max_speed: 200.0,
+                turn_rate: 5.0,
+                angular_accel: f64::INFINITY,
+            },
+            HandlingModel::Arcade => Handling {
+                model: HandlingModel::Arcade,
+                thrust: 150.0,
+                drag: 0.6,
+                max_speed: 150.0,
+                turn_rate: 5.0,
+                angular_accel: 40.0,
+            },
+            HandlingModel::FlightAssist => Handling {
+                model: HandlingModel::FlightAssist,
+                thrust: 120.0,
+                drag: 0.0,
+                max_speed: 200.0,
+                turn_rate: 4.0,
+                angular_accel: 20.0,
+            },
+        };
+        return self.tuning.apply(preset);
+    }
+
+    pub fn weapon(&self) -> Weapon {
+        return Weapon {
+            speed: 100.0,
+            range: 100.0,
+            cooldown: 0.5,
+            inherit_velocity: true,
+        };
+    }
+
+    // Whether bullets can hit the ship that fired them
+    pub fn friendly_fire(&self) -> bool {
+        return false;
+    }
+
     pub fn astroid_gap_distance(&self) -> f64 {
         return 25.0;
     }
 
+    pub fn astroid_collisions(&self) -> bool {
+        return self.astroid_collisions;
+    }
+
+    // Size of the cells used to find things that might be colliding
+    pub fn grid_cell_size(&self) -> f64 {
+        return 32.0;
+    }
+
+    pub fn armored_hits(&self) -> i64 {
+        return 3;
+    }
+
+    pub fn explosion_radius(&self) -> f64 {
+        return 30.0;
+    }
+
+    pub fn magnetic_pull(&self) -> f64 {
+        return 30.0;
+    }
+
+    // How close the ship has to come to an asteroid, and get away, to score a
+    // near miss
+    pub fn near_miss_distance(&self) -> f64 {
+        return self.near_miss;
+    }
+
+    // How far the ship starts each wave from the nearest asteroid
+    pub fn safe_spawn_distance(&self) -> f64 {
+        return 30.0;
+    }
+
     pub fn font_path(&self) -> &Path {
         return Path::new("res/Carlito-Regular.ttf");
     }
@@ -27,3 +303,92 @@ impl Config {
         return (-45.0, 0.0);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        return args
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<String>>()
+            .into_iter();
+    }
+
+    #[test]
+    fn test_from_args() {
+        let config = Config::from_args(args(&[])).unwrap();
+        expect!(config.boundary()).to(be_equal_to(Boundary::Wrap));
+        let config = Config::from_args(args(&["--boundary", "bounce"])).unwrap();
+        expect!(config.boundary()).to(be_equal_to(Boundary::Bounce));
+        assert!(Config::from_args(args(&["--boundary"])).is_err());
+        assert!(Config::from_args(args(&["--boundary", "sideways"])).is_err());
+        assert!(Config::from_args(args(&["--fast"])).is_err());
+        let config = Config::from_args(args(&["--handling", "assist"])).unwrap();
+        expect!(config.handling().model).to(be_equal_to(HandlingModel::FlightAssist));
+        assert!(Config::from_args(args(&["--handling", "floaty"])).is_err());
+        let tuned = [
+            "--thrust",
+            "300",
+            "--handling",
+            "arcade",
+            "--angular-accel",
+            "inf",
+        ];
+        let handling = Config::from_args(args(&tuned)).unwrap().handling();
+        expect!(handling.model).to(be_equal_to(HandlingModel::Arcade));
+        expect!(handling.thrust).to(be_equal_to(300.0));
+        expect!(handling.drag).to(be_equal_to(0.6));
+        expect!(handling.angular_accel).to(be_equal_to(f64::INFINITY));
+        assert!(Config::from_args(args(&["--max-speed", "-5"])).is_err());
+        assert!(Config::from_args(args(&["--drag", "NaN"])).is_err());
+        let config = Config::from_args(args(&["--arena", "res/arenas/pillar.txt"])).unwrap();
+        assert!(!config.arena().walls().is_empty());
+        assert!(Config::from_args(args(&["--arena", "res/arenas/missing.txt"])).is_err());
+        let config = Config::from_args(args(&["--near-miss", "8"])).unwrap();
+        expect!(config.near_miss_distance()).to(be_equal_to(8.0));
+        assert!(Config::from_args(args(&["--near-miss", "close"])).is_err());
+        assert!(!Config::new().astroid_collisions());
+        let config = Config::from_args(args(&["--astroid-collisions"])).unwrap();
+        assert!(config.astroid_collisions());
+        expect!(Config::new().command()).to(be_equal_to(Command::Play));
+        let config = Config::from_args(args(&["batch", "--worlds", "4", "--seed", "7"])).unwrap();
+        expect!(config.command()).to(be_equal_to(Command::Batch {
+            worlds: 4,
+            ticks: 3600,
+            seed: 7,
+        }));
+        assert!(Config::from_args(args(&["batch", "--ticks", "many"])).is_err());
+        assert!(Config::from_args(args(&["--worlds", "4"])).is_err());
+        assert!(Config::from_args(args(&["--seed", "4"])).is_err());
+        let config = Config::from_args(args(&["render-frame", "--tick", "60", "--seed", "3"]));
+        expect!(config.unwrap().command()).to(be_equal_to(Command::RenderFrame {
+            tick: 60,
+            out: "frame.png".to_string(),
+            seed: 3,
+        }));
+        let config = Config::from_args(args(&["render-frame", "--out", "shot.png"])).unwrap();
+        let Command::RenderFrame { out, .. } = config.command() else {
+            panic!("not rendering a frame");
+        };
+        expect!(out).to(be_equal_to("shot.png".to_string()));
+        assert!(Config::from_args(args(&["batch", "--tick", "60"])).is_err());
+        let config = Config::from_args(args(&["export", "crash.txt", "--fps", "10"])).unwrap();
+        expect!(config.command()).to(be_equal_to(Command::Export {
+            replay: "crash.txt".to_string(),
+            out: "replay.gif".to_string(),
+            fps: 10,
+            scale: 1.0,
+        }));
+        assert!(Config::from_args(args(&["export"])).is_err());
+        assert!(Config::from_args(args(&["--scale", "2"])).is_err());
+        expect!(Config::new().record_path(1)).to(be_none());
+        let config = Config::from_args(args(&["--record", "replays/game.txt"])).unwrap();
+        let path = PathBuf::from("replays/game-2.txt");
+        expect!(config.record_path(2)).to(be_some().value(path));
+        let config = Config::from_args(args(&["--record", "game"])).unwrap();
+        expect!(config.record_path(1)).to(be_some().value(PathBuf::from("game-1")));
+    }
+}
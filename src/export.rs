@@ -0,0 +1,259 @@
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::batch::TICK;
+use crate::config::Config;
+use crate::geometry::{Segment, Transform};
+use crate::raster::Rasterizer;
+use crate::render::{Color, Renderer, BLACK};
+use crate::replay::Replay;
+use crate::scene::{draw_game, draw_game_over};
+use crate::svg::SvgRenderer;
+use crate::world::{Outcome, World};
+
+// Turns replays into clips, drawn with the software renderer, or into a
+// time-lapse SVG. A clip ends when the replay runs out, or on the game over
+// screen if the ship is destroyed.
+
+// How the ship's path is drawn over a time-lapse
+const TRAIL: Color = [0.3, 0.7, 1.0, 1.0];
+// How see-through the first frame of a time-lapse is; later ones fade in
+const FIRST_FRAME_ALPHA: f32 = 0.1;
+// Largest scale clips can be drawn at, which keeps frames to a size that fits
+// in memory
+const MAX_SCALE: f64 = 16.0;
+// GIFs time frames in hundredths of a second, so can't show more frames a
+// second than this
+const MAX_GIF_FPS: u32 = 100;
+
+// Draws one frame, `scale` times the size of the screen
+fn render(world: &World, game_over: bool, scale: f64, config: &Config) -> RgbaImage {
+    let width = (config.width() * scale).round() as u32;
+    let height = (config.height() * scale).round() as u32;
+    let mut raster = Rasterizer::new(width, height);
+    raster.push_transform(Transform::scale(scale));
+    if game_over {
+        draw_game_over(world, config, &mut raster);
+    } else {
+        draw_game(world, config, &mut raster);
+    }
+    return raster.into_image();
+}
+
+// Plays the replay back, handing over the world `fps` times for each second
+// of it, along with whether the game's over. Returns how many frames there
+// were.
+fn play_back(
+    replay: &Replay,
+    fps: u32,
+    config: &Config,
+    mut frame: impl FnMut(&World, bool) -> Result<(), String>,
+) -> Result<usize, String> {
+    let ticks_per_frame = 1.0 / (fps as f64 * TICK);
+    let mut world = replay.start(config);
+    frame(&world, false)?;
+    let mut frames = 1;
+    for (tick, controls) in replay.ticks().enumerate() {
+        world.steer(controls);
+        let outcome = world.step(TICK, config);
+        if outcome == Outcome::WaveCleared {
+            world.next_wave(config);
+        }
+        let game_over = outcome == Outcome::ShipDestroyed;
+        if game_over || (tick + 1) as f64 >= frames as f64 * ticks_per_frame {
+            frame(&world, game_over)?;
+            frames += 1;
+        }
+        if game_over {
+            break;
+        }
+    }
+    return Ok(frames);
+}
+
+// The frames drawn over each other, fading in from the first, with the path
+// the ship took over the top
+fn time_lapse(worlds: &[World], scale: f64, config: &Config) -> SvgRenderer {
+    let mut svg = SvgRenderer::new(config.width() * scale, config.height() * scale);
+    svg.push_transform(Transform::scale(scale));
+    svg.clear(BLACK);
+    let fade_in = (1.0 - FIRST_FRAME_ALPHA) / (worlds.len() - 1).max(1) as f32;
+    for (i, world) in worlds.iter().enumerate() {
+        let alpha = FIRST_FRAME_ALPHA + fade_in * i as f32;
+        world.draw([1.0, 1.0, 1.0, alpha], config, &mut svg);
+    }
+    // Jumps across a wrapping edge aren't part of the path
+    let bounds = config.bounds();
+    for pair in worlds.windows(2) {
+        let (from, to) = (
+            pair[0].spaceship().position(),
+            pair[1].spaceship().position(),
+        );
+        if bounds.delta(from, to) == to - from {
+            svg.line(TRAIL, 0.5, Segment::new(from, to));
+        }
+    }
+    return svg;
+}
+
+// When the frame starts in a GIF, in hundredths of a second. Each frame's
+// delay is what's left until the next one starts, so the rounding doesn't add
+// up over the clip.
+fn centiseconds(frame: usize, fps: u32) -> u32 {
+    return (frame as f64 * 100.0 / fps as f64).round() as u32;
+}
+
+// Where the frame goes in a numbered sequence, e.g. clip-00012.png for
+// clip.png
+fn numbered(path: &Path, frame: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    return path.with_file_name(format!("{}-{:05}.png", stem, frame));
+}
+
+// Saves the replay as an animated GIF if `out` ends in .gif, as a numbered
+// PNG for each frame if it ends in .png, or as a time-lapse of every frame if
+// it ends in .svg. Returns how many frames there were.
+pub fn export(
+    replay: &Replay,
+    out: &Path,
+    fps: u32,
+    scale: f64,
+    config: &Config,
+) -> Result<usize, String> {
+    let failed = |e: image::ImageError| format!("couldn't save {}: {}", out.display(), e);
+    // A frame a tick is as many as there are
+    let max_fps = (1.0 / TICK).round() as u32;
+    if fps == 0 || fps > max_fps {
+        return Err(format!("clips need a frame rate from 1 to {}", max_fps));
+    }
+    if !(scale > 0.0 && scale <= MAX_SCALE) {
+        return Err(format!(
+            "clips need a scale above 0 and up to {}",
+            MAX_SCALE
+        ));
+    }
+    return match out.extension().and_then(|e| e.to_str()) {
+        Some("gif") => {
+            if fps > MAX_GIF_FPS {
+                return Err(format!("GIFs need a frame rate up to {}", MAX_GIF_FPS));
+            }
+            let file =
+                File::create(out).map_err(|e| format!("couldn't save {}: {}", out.display(), e))?;
+            let mut gif = GifEncoder::new(file);
+            gif.set_repeat(Repeat::Infinite).map_err(failed)?;
+            let mut shown = 0;
+            play_back(replay, fps, config, |world, game_over| {
+                let image = render(world, game_over, scale, config);
+                let length = centiseconds(shown + 1, fps) - centiseconds(shown, fps);
+                let delay = Delay::from_numer_denom_ms(length * 10, 1);
+                shown += 1;
+                return gif
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .map_err(failed);
+            })
+        }
+        Some("png") => {
+            let mut saved = 0;
+            play_back(replay, fps, config, |world, game_over| {
+                let path = numbered(out, saved);
+                saved += 1;
+                return render(world, game_over, scale, config)
+                    .save(&path)
+                    .map_err(|e| format!("couldn't save {}: {}", path.display(), e));
+            })
+        }
+        Some("svg") => {
+            let mut worlds = Vec::new();
+            let frames = play_back(replay, fps, config, |world, _| {
+                worlds.push(world.clone());
+                return Ok(());
+            })?;
+            time_lapse(&worlds, scale, config).save(out)?;
+            Ok(frames)
+        }
+        _ => Err(format!(
+            "{} needs to end in .gif, .png or .svg",
+            out.display()
+        )),
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actors::Controls;
+    use expectest::prelude::*;
+
+    #[test]
+    fn test_numbered() {
+        let path = numbered(Path::new("clips/crash.png"), 12);
+        expect!(path).to(be_equal_to(PathBuf::from("clips/crash-00012.png")));
+    }
+
+    #[test]
+    fn test_centiseconds() {
+        // A second of frames at 30 a second lasts a second, not 0.9 of one
+        let delays: Vec<u32> = (0..30)
+            .map(|i| centiseconds(i + 1, 30) - centiseconds(i, 30))
+            .collect();
+        expect!(delays.iter().sum::<u32>()).to(be_equal_to(100));
+        assert!(delays.iter().all(|&d| d == 3 || d == 4));
+        // No frame is skipped at the fastest rate
+        assert!((0..100).all(|i| centiseconds(i + 1, MAX_GIF_FPS) > centiseconds(i, MAX_GIF_FPS)));
+    }
+
+    #[test]
+    fn test_play_back() {
+        let config = Config::new();
+        let mut replay = Replay::new(1, 3);
+        for _ in 0..120 {
+            replay.record(Controls::default());
+        }
+        // A second at 30 frames a second, after the first frame
+        let mut sizes = Vec::new();
+        let frames = play_back(&replay, 30, &config, |world, game_over| {
+            sizes.push(render(world, game_over, 2.0, &config).dimensions());
+            return Ok(());
+        });
+        expect!(frames).to(be_ok().value(31));
+        expect!(sizes.len()).to(be_equal_to(31));
+        expect!(sizes[0]).to(be_equal_to((400, 400)));
+        let out = Path::new("clip.mp4");
+        assert!(export(&replay, out, 30, 1.0, &config).is_err());
+        let gif = Path::new("clip.gif");
+        assert!(export(&replay, gif, 0, 1.0, &config).is_err());
+        assert!(export(&replay, gif, 110, 1.0, &config).is_err());
+        assert!(export(&replay, Path::new("clip.png"), 121, 1.0, &config).is_err());
+        assert!(export(&replay, gif, 30, f64::NAN, &config).is_err());
+        assert!(export(&replay, gif, 30, 1e4, &config).is_err());
+    }
+
+    #[test]
+    fn test_time_lapse() {
+        let config = Config::new();
+        let mut replay = Replay::new(1, 3);
+        for _ in 0..120 {
+            replay.record(Controls {
+                thrust: 1.0,
+                turn: 0.0,
+                fire: false,
+            });
+        }
+        let mut worlds = Vec::new();
+        play_back(&replay, 10, &config, |world, _| {
+            worlds.push(world.clone());
+            return Ok(());
+        })
+        .unwrap();
+        expect!(worlds.len()).to(be_equal_to(11));
+        let svg = time_lapse(&worlds, 2.0, &config).to_svg();
+        assert!(svg.contains("width=\"400\" height=\"400\""));
+        // The ship in every frame, the first faintest and the last solid,
+        // and the path between them
+        expect!(svg.matches("<polygon").count()).to(be_equal_to(11));
+        assert!(svg.contains("fill-opacity=\"0.1\""));
+        expect!(svg.matches("stroke=\"rgb(77,179,255)\"").count()).to(be_equal_to(10));
+    }
+}
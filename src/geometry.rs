@@ -0,0 +1,548 @@
+use crate::intersect::{intersection_time, lines_intersect};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+// Vectors, line segments, polygons and transforms. Headings are in radians
+// clockwise from straight up the screen, which is the direction of -y.
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub fn new(x: f64, y: f64) -> Self {
+        Vec2 { x: x, y: y }
+    }
+
+    pub fn zero() -> Vec2 {
+        return Vec2::new(0.0, 0.0);
+    }
+
+    // A vector of length r pointing along the heading
+    pub fn from_heading(theta: f64, r: f64) -> Vec2 {
+        return Vec2::new(theta.sin() * r, -theta.cos() * r);
+    }
+
+    // Which way the vector points, the inverse of from_heading
+    pub fn heading(self) -> f64 {
+        return self.x.atan2(-self.y);
+    }
+
+    pub fn cross(self, other: Vec2) -> f64 {
+        return self.x * other.y - other.x * self.y;
+    }
+
+    pub fn dot(self, other: Vec2) -> f64 {
+        return self.x * other.x + self.y * other.y;
+    }
+
+    pub fn length(self) -> f64 {
+        return self.dot(self).sqrt();
+    }
+
+    // The vector scaled to length 1, or None if it has no length
+    pub fn normalize(self) -> Option<Vec2> {
+        let length = self.length();
+        if length == 0.0 {
+            return None;
+        }
+        return Some(self / length);
+    }
+
+    // The same vector turned a quarter turn, clockwise on the screen
+    pub fn perpendicular(self) -> Vec2 {
+        return Vec2::new(-self.y, self.x);
+    }
+
+    // Turns the vector clockwise on the screen by theta radians
+    pub fn rotate(self, theta: f64) -> Vec2 {
+        let (sin, cos) = theta.sin_cos();
+        return Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos);
+    }
+
+    // The point a fraction t of the way from self to other
+    pub fn lerp(self, other: Vec2, t: f64) -> Vec2 {
+        return self + (other - self) * t;
+    }
+}
+
+impl Add<Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Self::Output {
+        return Vec2 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        };
+    }
+}
+
+impl AddAssign<Vec2> for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl Mul<f64> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, other: f64) -> Self::Output {
+        return Vec2 {
+            x: self.x * other,
+            y: self.y * other,
+        };
+    }
+}
+
+impl Sub<Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Self::Output {
+        return Vec2 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        };
+    }
+}
+
+impl SubAssign<Vec2> for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+
+impl Div<f64> for Vec2 {
+    type Output = Vec2;
+
+    fn div(self, other: f64) -> Self::Output {
+        return Vec2 {
+            x: self.x / other,
+            y: self.y / other,
+        };
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Self::Output {
+        return Vec2 {
+            x: -self.x,
+            y: -self.y,
+        };
+    }
+}
+
+// A straight line between two points
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Segment {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Segment {
+    pub fn new(start: Vec2, end: Vec2) -> Segment {
+        return Segment {
+            start: start,
+            end: end,
+        };
+    }
+
+    // From the start to the end
+    pub fn along(self) -> Vec2 {
+        return self.end - self.start;
+    }
+
+    pub fn length(self) -> f64 {
+        return self.along().length();
+    }
+
+    // Unit vector a quarter turn clockwise from the segment's direction, or
+    // None if it has no length
+    pub fn normal(self) -> Option<Vec2> {
+        return self.along().perpendicular().normalize();
+    }
+
+    // The point a fraction t of the way along
+    pub fn at(self, t: f64) -> Vec2 {
+        return self.start.lerp(self.end, t);
+    }
+
+    pub fn translate(self, offset: Vec2) -> Segment {
+        return Segment::new(self.start + offset, self.end + offset);
+    }
+
+    pub fn reversed(self) -> Segment {
+        return Segment::new(self.end, self.start);
+    }
+
+    pub fn intersects(self, other: Segment) -> bool {
+        return lines_intersect(self, other);
+    }
+
+    // How far along this segment, from 0 to 1, it first touches the other
+    pub fn intersection_time(self, other: Segment) -> Option<f64> {
+        return intersection_time(self, other);
+    }
+}
+
+// Segments convert to and from piston's lines, [x1, y1, x2, y2]
+impl From<[f64; 4]> for Segment {
+    fn from(line: [f64; 4]) -> Segment {
+        return Segment::new(Vec2::new(line[0], line[1]), Vec2::new(line[2], line[3]));
+    }
+}
+
+impl From<Segment> for [f64; 4] {
+    fn from(segment: Segment) -> [f64; 4] {
+        let Segment { start, end } = segment;
+        return [start.x, start.y, end.x, end.y];
+    }
+}
+
+// A closed outline through a list of corners
+#[derive(Clone, PartialEq, Debug)]
+pub struct Polygon {
+    points: Vec<Vec2>,
+}
+
+// The smallest and largest coordinates of any of the points
+pub fn bounding_box(points: &[Vec2]) -> (Vec2, Vec2) {
+    let infinity = Vec2::new(f64::INFINITY, f64::INFINITY);
+    return points.iter().fold((infinity, -infinity), |(min, max), p| {
+        (
+            Vec2::new(min.x.min(p.x), min.y.min(p.y)),
+            Vec2::new(max.x.max(p.x), max.y.max(p.y)),
+        )
+    });
+}
+
+impl Polygon {
+    pub fn new(points: Vec<Vec2>) -> Polygon {
+        return Polygon { points: points };
+    }
+
+    // A square centred on the origin
+    #[cfg(test)]
+    pub fn square(half: f64) -> Polygon {
+        return Polygon::new(vec![
+            Vec2::new(-half, -half),
+            Vec2::new(half, -half),
+            Vec2::new(half, half),
+            Vec2::new(-half, half),
+        ]);
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        return &self.points;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.points.len();
+    }
+
+    // Every side, including the one from the last corner back to the first
+    pub fn edges(&self) -> impl Iterator<Item = Segment> + Clone + '_ {
+        return self
+            .points
+            .iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .map(|(&p1, &p2)| Segment::new(p1, p2));
+    }
+
+    // Positive when the corners go clockwise on the screen, by the shoelace
+    // formula
+    pub fn signed_area(&self) -> f64 {
+        return self.edges().map(|e| e.start.cross(e.end)).sum::<f64>() / 2.0;
+    }
+
+    pub fn area(&self) -> f64 {
+        return self.signed_area().abs();
+    }
+
+    // Area-weighted centroid, see: https://en.wikipedia.org/wiki/Centroid#Of_a_polygon
+    // A polygon with no area falls back to the average of its corners.
+    pub fn centroid(&self) -> Vec2 {
+        let mut twice_area = 0.0;
+        let mut sum = Vec2::zero();
+        for edge in self.edges() {
+            let cross = edge.start.cross(edge.end);
+            twice_area += cross;
+            sum += (edge.start + edge.end) * cross;
+        }
+        if twice_area == 0.0 {
+            return self.points.iter().fold(Vec2::zero(), |a, &p| a + p) / self.len() as f64;
+        }
+        return sum / (3.0 * twice_area);
+    }
+
+    // Distance from the origin to the furthest corner
+    pub fn radius(&self) -> f64 {
+        return self.points.iter().map(|p| p.length()).fold(0.0, f64::max);
+    }
+
+    // Whether every corner turns the same way. Straight corners don't count.
+    pub fn is_convex(&self) -> bool {
+        let n = self.len();
+        let turns = (0..n).map(|i| {
+            let (a, b, c) = (
+                self.points[(i + n - 1) % n],
+                self.points[i],
+                self.points[(i + 1) % n],
+            );
+            (b - a).cross(c - b)
+        });
+        let (mut left, mut right) = (false, false);
+        for turn in turns {
+            left |= turn > 0.0;
+            right |= turn < 0.0;
+        }
+        return !(left && right);
+    }
+
+    pub fn translate(&self, offset: Vec2) -> Polygon {
+        return self.transform(&Transform::translation(offset));
+    }
+
+    pub fn transform(&self, transform: &Transform) -> Polygon {
+        return Polygon::new(self.points.iter().map(|&p| transform.apply(p)).collect());
+    }
+}
+
+// An affine transform: a rotation and scale followed by a translation
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Transform {
+    // Where the x and y axes end up
+    x_axis: Vec2,
+    y_axis: Vec2,
+    translation: Vec2,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        return Transform {
+            x_axis: Vec2::new(1.0, 0.0),
+            y_axis: Vec2::new(0.0, 1.0),
+            translation: Vec2::zero(),
+        };
+    }
+
+    pub fn translation(offset: Vec2) -> Transform {
+        return Transform {
+            translation: offset,
+            ..Transform::identity()
+        };
+    }
+
+    // Turns clockwise on the screen by theta radians about the origin
+    pub fn rotation(theta: f64) -> Transform {
+        return Transform {
+            x_axis: Vec2::new(1.0, 0.0).rotate(theta),
+            y_axis: Vec2::new(0.0, 1.0).rotate(theta),
+            translation: Vec2::zero(),
+        };
+    }
+
+    // Grows everything by the factor, away from the origin
+    pub fn scale(factor: f64) -> Transform {
+        return Transform {
+            x_axis: Vec2::new(factor, 0.0),
+            y_axis: Vec2::new(0.0, factor),
+            translation: Vec2::zero(),
+        };
+    }
+
+    // Rotated by theta and then moved to position, like a sprite
+    pub fn placement(position: Vec2, theta: f64) -> Transform {
+        return Transform::rotation(theta).then(&Transform::translation(position));
+    }
+
+    pub fn apply(&self, p: Vec2) -> Vec2 {
+        return self.x_axis * p.x + self.y_axis * p.y + self.translation;
+    }
+
+    // Applies only the rotation and scale, as for a direction
+    pub fn apply_vector(&self, v: Vec2) -> Vec2 {
+        return self.x_axis * v.x + self.y_axis * v.y;
+    }
+
+    // How much lengths grow, on average over every direction
+    pub fn stretch(&self) -> f64 {
+        return self.x_axis.cross(self.y_axis).abs().sqrt();
+    }
+
+    // As piston's matrices, [[xx, yx, tx], [xy, yy, ty]]
+    pub fn matrix(&self) -> [[f64; 3]; 2] {
+        let Transform {
+            x_axis,
+            y_axis,
+            translation,
+        } = *self;
+        return [
+            [x_axis.x, y_axis.x, translation.x],
+            [x_axis.y, y_axis.y, translation.y],
+        ];
+    }
+
+    // This transform followed by the other one
+    pub fn then(&self, other: &Transform) -> Transform {
+        return Transform {
+            x_axis: other.apply_vector(self.x_axis),
+            y_axis: other.apply_vector(self.y_axis),
+            translation: other.apply(self.translation),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+    use std::f64::consts::PI;
+
+    const P1: Vec2 = Vec2 { x: 2.0, y: 3.0 };
+    const P1_TIMES_2: Vec2 = Vec2 { x: 4.0, y: 6.0 };
+    const P2: Vec2 = Vec2 { x: 5.0, y: 7.0 };
+
+    fn expect_close(actual: Vec2, expected: Vec2) {
+        expect!(actual.x).to(be_close_to(expected.x));
+        expect!(actual.y).to(be_close_to(expected.y));
+    }
+
+    #[test]
+    fn test_new() {
+        expect!(Vec2::new(2.0, 3.0)).to(be_equal_to(P1));
+    }
+
+    #[test]
+    fn test_cross() {
+        expect!(P1.cross(P2)).to(be_equal_to(-1.0));
+    }
+
+    #[test]
+    fn test_dot() {
+        expect!(P1.dot(P2)).to(be_equal_to(31.0));
+    }
+
+    #[test]
+    fn test_ops() {
+        expect!(P1 + P1).to(be_equal_to(P1_TIMES_2));
+        expect!(P1_TIMES_2 - P1).to(be_equal_to(P1));
+        expect!(P1_TIMES_2 / 2.0).to(be_equal_to(P1));
+        expect!(P1 * 2.0).to(be_equal_to(P1_TIMES_2));
+        expect!(-P1).to(be_equal_to(Vec2::new(-2.0, -3.0)));
+        let mut p = P1;
+        p += P1;
+        expect!(p).to(be_equal_to(P1_TIMES_2));
+        p -= P1;
+        expect!(p).to(be_equal_to(P1));
+    }
+
+    #[test]
+    fn test_headings() {
+        expect_close(Vec2::from_heading(0.0, 1.0), Vec2::new(0.0, -1.0));
+        expect_close(Vec2::from_heading(PI / 2.0, 2.0), Vec2::new(2.0, 0.0));
+        expect!(Vec2::new(0.0, -1.0).heading()).to(be_close_to(0.0));
+        expect!(Vec2::new(1.0, 0.0).heading()).to(be_close_to(PI / 2.0));
+    }
+
+    #[test]
+    fn test_length_and_normalize() {
+        expect!(Vec2::new(3.0, 4.0).length()).to(be_equal_to(5.0));
+        expect!(Vec2::new(3.0, 4.0).normalize()).to(be_some().value(Vec2::new(0.6, 0.8)));
+        expect!(Vec2::zero().normalize()).to(be_none());
+    }
+
+    #[test]
+    fn test_rotate_and_lerp() {
+        // A quarter turn clockwise on the screen takes up to right
+        expect_close(Vec2::new(0.0, -1.0).rotate(PI / 2.0), Vec2::new(1.0, 0.0));
+        expect_close(
+            Vec2::from_heading(0.3, 1.0).rotate(0.4),
+            Vec2::from_heading(0.7, 1.0),
+        );
+        expect!(P1.lerp(P2, 0.5)).to(be_equal_to(Vec2::new(3.5, 5.0)));
+    }
+
+    #[test]
+    fn test_segment() {
+        let segment = Segment::new(Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0));
+        expect!(segment.length()).to(be_equal_to(4.0));
+        expect!(segment.at(0.25)).to(be_equal_to(Vec2::new(1.0, 0.0)));
+        expect!(segment.normal()).to(be_some().value(Vec2::new(0.0, 1.0)));
+        let crossing = Segment::new(Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0));
+        assert!(segment.intersects(crossing));
+        expect!(segment.intersection_time(crossing)).to(be_some().value(0.25));
+        assert!(!segment.intersects(crossing.translate(Vec2::new(5.0, 0.0))));
+        expect!(<[f64; 4]>::from(segment)).to(be_equal_to([0.0, 0.0, 4.0, 0.0]));
+    }
+
+    #[test]
+    fn test_polygon() {
+        let square = Polygon::square(5.0);
+        expect!(square.area()).to(be_close_to(100.0));
+        expect!(square.edges().count()).to(be_equal_to(4));
+        expect_close(square.centroid(), Vec2::zero());
+        expect!(bounding_box(square.points()))
+            .to(be_equal_to((Vec2::new(-5.0, -5.0), Vec2::new(5.0, 5.0))));
+        assert!(square.is_convex());
+        let moved = square.translate(Vec2::new(1.0, 2.0));
+        expect_close(moved.centroid(), Vec2::new(1.0, 2.0));
+        let triangle = Polygon::new(vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(3.0, 0.0),
+            Vec2::new(0.0, 3.0),
+        ]);
+        expect!(triangle.area()).to(be_close_to(4.5));
+        expect_close(triangle.centroid(), Vec2::new(1.0, 1.0));
+        let right_angled = Polygon::new(vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(0.0, 3.0),
+        ]);
+        expect!(right_angled.area()).to(be_close_to(6.0));
+        expect!(right_angled.radius()).to(be_equal_to(4.0));
+        let notched = Polygon::new(vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(4.0, 4.0),
+            Vec2::new(0.0, 4.0),
+        ]);
+        assert!(!notched.is_convex());
+    }
+
+    #[test]
+    fn test_transform() {
+        let p = Vec2::new(1.0, 0.0);
+        expect!(Transform::identity().apply(p)).to(be_equal_to(p));
+        expect_close(Transform::rotation(PI / 2.0).apply(p), Vec2::new(0.0, 1.0));
+        // Rotating then moving isn't the same as moving then rotating
+        let moved = Transform::translation(Vec2::new(10.0, 0.0));
+        let turned = Transform::rotation(PI);
+        expect_close(turned.then(&moved).apply(p), Vec2::new(9.0, 0.0));
+        expect_close(moved.then(&turned).apply(p), Vec2::new(-11.0, 0.0));
+        expect_close(
+            Transform::placement(Vec2::new(10.0, 0.0), PI).apply(p),
+            Vec2::new(9.0, 0.0),
+        );
+        // Directions don't move
+        expect!(moved.apply_vector(p)).to(be_equal_to(p));
+        expect!(turned.then(&moved).stretch()).to(be_close_to(1.0));
+        let grown = Transform::scale(3.0).then(&moved);
+        expect_close(grown.apply(p), Vec2::new(13.0, 0.0));
+        expect!(grown.stretch()).to(be_close_to(3.0));
+        // Piston's matrices agree
+        let m = Transform::placement(Vec2::new(10.0, 20.0), PI / 2.0).matrix();
+        let q = Vec2::new(3.0, 4.0);
+        let applied = Vec2::new(
+            m[0][0] * q.x + m[0][1] * q.y + m[0][2],
+            m[1][0] * q.x + m[1][1] * q.y + m[1][2],
+        );
+        expect_close(
+            applied,
+            Transform::placement(Vec2::new(10.0, 20.0), PI / 2.0).apply(q),
+        );
+    }
+}
@@ -0,0 +1,194 @@
+// Renders seeded games with the software renderer and compares them against
+// reference images in res/golden, so changes to how things are drawn don't go
+// unnoticed. Small differences, like anti-aliasing that rounds the other way,
+// are tolerated. When a frame doesn't match, what was drawn and a diff
+// showing where it differs are written to target/golden.
+//
+// After changing how something looks on purpose, run the tests with
+// UPDATE_GOLDEN=1 to replace the references, and check the new ones in.
+
+use image::{Rgba, RgbaImage};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::path::Path;
+
+use crate::actors::Controls;
+use crate::batch::TICK;
+use crate::config::Config;
+use crate::raster::Rasterizer;
+use crate::render::Renderer;
+use crate::scene::{draw_game, draw_game_over};
+use crate::world::{Outcome, World};
+
+// How far apart, from 0 to 1, a pixel's brightness can be and still match
+const TOLERANCE: f32 = 0.1;
+// How many pixels can be further apart than that before the frame differs
+const MAX_DIFFERENCES: usize = 20;
+
+const REFERENCES: &str = "res/golden";
+const FAILURES: &str = "target/golden";
+
+fn brightness(Rgba([r, g, b, a]): Rgba<u8>) -> f32 {
+    let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    return luma * a as f32 / (255.0 * 255.0);
+}
+
+// A picture of where the frames differ, in red over a faded copy of the
+// reference. Returns how many pixels differ, along with the picture.
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> (usize, RgbaImage) {
+    let mut differences = 0;
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    for (x, y, pixel) in diff.enumerate_pixels_mut() {
+        let faded = (brightness(*expected.get_pixel(x, y)) * 64.0) as u8;
+        let apart =
+            (brightness(*expected.get_pixel(x, y)) - brightness(*actual.get_pixel(x, y))).abs();
+        *pixel = if apart > TOLERANCE {
+            differences += 1;
+            Rgba([128 + (apart * 127.0) as u8, 0, 0, 255])
+        } else {
+            Rgba([faded, faded, faded, 255])
+        };
+    }
+    return (differences, diff);
+}
+
+fn check(name: &str, actual: &RgbaImage) {
+    let reference = Path::new(REFERENCES).join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&reference).unwrap();
+        return;
+    }
+    let expected = match image::open(&reference) {
+        Ok(expected) => expected.to_rgba8(),
+        Err(e) => panic!(
+            "couldn't open {}: {}; run with UPDATE_GOLDEN=1 to make it",
+            reference.display(),
+            e
+        ),
+    };
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "{} changed size",
+        name
+    );
+    let (differences, diff) = compare(&expected, actual);
+    if differences <= MAX_DIFFERENCES {
+        return;
+    }
+    fs::create_dir_all(FAILURES).unwrap();
+    let failed = Path::new(FAILURES).join(format!("{}.png", name));
+    let diffed = Path::new(FAILURES).join(format!("{}-diff.png", name));
+    actual.save(&failed).unwrap();
+    diff.save(&diffed).unwrap();
+    panic!(
+        "{} differs from {} in {} pixels; see {} and {}",
+        name,
+        reference.display(),
+        differences,
+        failed.display(),
+        diffed.display()
+    );
+}
+
+fn render(config: &Config, draw: impl FnOnce(&mut dyn Renderer)) -> RgbaImage {
+    let mut raster = Rasterizer::new(config.width() as u32, config.height() as u32);
+    draw(&mut raster);
+    return raster.into_image();
+}
+
+#[test]
+fn test_compare() {
+    let mut expected = RgbaImage::new(4, 4);
+    let mut actual = expected.clone();
+    // Slightly brighter is close enough
+    actual.put_pixel(1, 1, Rgba([10, 10, 10, 255]));
+    expect_differences(&expected, &actual, 0);
+    actual.put_pixel(2, 2, Rgba([255, 255, 255, 255]));
+    expect_differences(&expected, &actual, 1);
+    expected.put_pixel(2, 2, Rgba([255, 255, 255, 255]));
+    expect_differences(&expected, &actual, 0);
+}
+
+fn expect_differences(expected: &RgbaImage, actual: &RgbaImage, count: usize) {
+    let (differences, diff) = compare(expected, actual);
+    assert_eq!(differences, count);
+    assert_eq!(diff.pixels().filter(|p| p[0] >= 128).count(), count);
+}
+
+#[test]
+fn test_wave_start() {
+    let config = Config::new();
+    let world = World::new(1, &config, StdRng::seed_from_u64(1));
+    check(
+        "wave_start",
+        &render(&config, |r| draw_game(&world, &config, r)),
+    );
+}
+
+// The ship turned part of the way round, so its outline only looks right if
+// it's turned along with it
+#[test]
+fn test_turning_ship() {
+    let config = Config::new();
+    let mut world = World::new(3, &config, StdRng::seed_from_u64(2));
+    world.steer(Controls {
+        thrust: 0.5,
+        turn: 1.0,
+        fire: false,
+    });
+    for _ in 0..30 {
+        world.step(TICK, &config);
+    }
+    check(
+        "turning_ship",
+        &render(&config, |r| draw_game(&world, &config, r)),
+    );
+}
+
+// Just after the first hit, with the pieces flying apart
+#[test]
+fn test_explosion() {
+    let config = Config::new();
+    let mut world = World::new(2, &config, StdRng::seed_from_u64(5));
+    world.steer(Controls {
+        thrust: 0.0,
+        turn: 1.0,
+        fire: true,
+    });
+    let mut ticks = 0;
+    while world.hits() == 0 {
+        assert!(ticks < 1200, "nothing was hit");
+        assert_eq!(world.step(TICK, &config), Outcome::Playing);
+        ticks += 1;
+    }
+    world.steer(Controls::default());
+    for _ in 0..10 {
+        world.step(TICK, &config);
+    }
+    check(
+        "explosion",
+        &render(&config, |r| draw_game(&world, &config, r)),
+    );
+}
+
+#[test]
+fn test_game_over() {
+    let config = Config::new();
+    let mut world = World::new(3, &config, StdRng::seed_from_u64(4));
+    world.steer(Controls {
+        thrust: 1.0,
+        turn: 0.2,
+        fire: true,
+    });
+    let mut ticks = 0;
+    while world.step(TICK, &config) != Outcome::ShipDestroyed {
+        assert!(ticks < 3600, "the ship wasn't destroyed");
+        ticks += 1;
+    }
+    check(
+        "game_over",
+        &render(&config, |r| draw_game_over(&world, &config, r)),
+    );
+}
@@ -0,0 +1,118 @@
+use crate::bounds::Bounds;
+use crate::geometry::{Segment, Vec2};
+use crate::render::{Color, Renderer};
+use std::f64::consts::PI;
+
+const HORIZON_SIDES: usize = 16;
+
+// A point that pulls everything toward it with an inverse-square force.
+// Anything that comes within the event horizon is gone for good.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GravityWell {
+    position: Vec2,
+    strength: f64,
+    horizon: f64,
+}
+
+impl GravityWell {
+    pub fn new(position: Vec2, strength: f64, horizon: f64) -> GravityWell {
+        return GravityWell {
+            position: position,
+            strength: strength,
+            horizon: horizon,
+        };
+    }
+
+    // The acceleration of something at p. It's capped at the strength of the
+    // pull at the event horizon so things don't shoot off to infinity if they
+    // pass right by a well without a horizon.
+    pub fn pull(&self, p: Vec2, bounds: &Bounds) -> Vec2 {
+        let d = bounds.delta(p, self.position);
+        let distance = d.length();
+        if distance == 0.0 {
+            return Vec2::zero();
+        }
+        let softened = distance.max(self.horizon).max(1.0);
+        return d * (self.strength / (softened * softened * distance));
+    }
+
+    pub fn swallows(&self, p: Vec2, bounds: &Bounds) -> bool {
+        let d = bounds.delta(p, self.position);
+        return d.dot(d) < self.horizon * self.horizon;
+    }
+
+    pub fn draw(&self, color: Color, renderer: &mut dyn Renderer) {
+        let corner = |i: usize| {
+            let theta = 2.0 * PI * i as f64 / HORIZON_SIDES as f64;
+            self.position + Vec2::new(theta.cos(), theta.sin()) * self.horizon
+        };
+        for i in 0..HORIZON_SIDES {
+            renderer.line(color, 1.0, Segment::new(corner(i), corner(i + 1)));
+        }
+    }
+}
+
+// The total pull of every well on something at p
+pub fn total_pull(wells: &[GravityWell], p: Vec2, bounds: &Bounds) -> Vec2 {
+    return wells
+        .iter()
+        .fold(Vec2::zero(), |total, well| total + well.pull(p, bounds));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actors::GameObject;
+    use crate::bounds::TEST_BOUNDS;
+    use expectest::prelude::*;
+
+    #[test]
+    fn test_inverse_square() {
+        let well = GravityWell::new(Vec2::new(100.0, 100.0), 1000.0, 5.0);
+        let near = well.pull(Vec2::new(90.0, 100.0), &TEST_BOUNDS);
+        let far = well.pull(Vec2::new(100.0, 120.0), &TEST_BOUNDS);
+        expect!(near.x).to(be_close_to(10.0));
+        expect!(near.y).to(be_close_to(0.0));
+        expect!(far.y).to(be_close_to(-2.5));
+        // Capped inside the horizon
+        let inside = well.pull(Vec2::new(98.0, 100.0), &TEST_BOUNDS);
+        expect!(inside.x).to(be_close_to(40.0));
+    }
+
+    #[test]
+    fn test_pull_wraps() {
+        let well = GravityWell::new(Vec2::new(5.0, 100.0), 1000.0, 5.0);
+        let pull = well.pull(Vec2::new(195.0, 100.0), &TEST_BOUNDS);
+        expect!(pull.x).to(be_close_to(10.0));
+    }
+
+    #[test]
+    fn test_total_pull() {
+        let wells = [
+            GravityWell::new(Vec2::new(50.0, 100.0), 1000.0, 5.0),
+            GravityWell::new(Vec2::new(150.0, 100.0), 1000.0, 5.0),
+        ];
+        let pull = total_pull(&wells, Vec2::new(100.0, 100.0), &TEST_BOUNDS);
+        expect!(pull.x).to(be_close_to(0.0));
+    }
+
+    #[test]
+    fn test_swallows() {
+        let well = GravityWell::new(Vec2::new(100.0, 100.0), 1000.0, 5.0);
+        assert!(well.swallows(Vec2::new(103.0, 103.0), &TEST_BOUNDS));
+        assert!(!well.swallows(Vec2::new(104.0, 104.0), &TEST_BOUNDS));
+    }
+
+    #[test]
+    fn test_passing_objects_curve_toward_well() {
+        let wells = [GravityWell::new(Vec2::new(100.0, 100.0), 4000.0, 6.0)];
+        // Flying right, passing just above the well
+        let mut obj = GameObject::new(Vec2::new(40.0, 80.0), Vec2::new(100.0, 0.0));
+        for _ in 0..120 {
+            obj.apply_force(total_pull(&wells, obj.position(), &TEST_BOUNDS));
+            obj.go(1.0 / 120.0, &TEST_BOUNDS);
+        }
+        assert!(obj.velocity().y > 0.0);
+        assert!(obj.position().y > 80.0);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::bounds::{Boundary, Bounds};
+use crate::geometry::Vec2;
+
+// A uniform grid over the arena for finding things that might be touching
+// without testing every pair. Each thing is added as a circle to every cell
+// the circle covers, so anything that could overlap a query circle shares a
+// cell with it. When the arena wraps, so do the cells. Otherwise anything past
+// the edge of the arena lands in the nearest cell inside it.
+#[derive(Clone, Debug)]
+pub struct SpatialGrid {
+    cell_width: f64,
+    cell_height: f64,
+    columns: usize,
+    rows: usize,
+    wraps: bool,
+    cells: Vec<Vec<usize>>,
+}
+
+// Whether two circles overlap, measured the short way around if the arena wraps
+pub fn circles_overlap(bounds: &Bounds, a: Vec2, a_radius: f64, b: Vec2, b_radius: f64) -> bool {
+    let d = bounds.delta(a, b);
+    let reach = a_radius + b_radius;
+    return d.dot(d) <= reach * reach;
+}
+
+// The cells covered by [low, high] along one axis. Wrapping cells are whole
+// numbers of cells across the arena so that they line up at the seam.
+fn cell_span(low: f64, high: f64, size: f64, count: usize, wraps: bool) -> (isize, isize) {
+    let first = (low / size).floor() as isize;
+    let last = (high / size).floor() as isize;
+    if wraps {
+        if last - first + 1 >= count as isize {
+            return (0, count as isize - 1);
+        }
+        return (first, last);
+    }
+    let clamp = |i: isize| i.clamp(0, count as isize - 1);
+    return (clamp(first), clamp(last));
+}
+
+impl SpatialGrid {
+    pub fn new(bounds: &Bounds, cell_size: f64) -> SpatialGrid {
+        let columns = ((bounds.width / cell_size).floor() as usize).max(1);
+        let rows = ((bounds.height / cell_size).floor() as usize).max(1);
+        return SpatialGrid {
+            cell_width: bounds.width / columns as f64,
+            cell_height: bounds.height / rows as f64,
+            columns: columns,
+            rows: rows,
+            wraps: bounds.boundary == Boundary::Wrap,
+            cells: vec![Vec::new(); columns * rows],
+        };
+    }
+
+    // Empties the grid, keeping the memory for the next tick
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+    }
+
+    fn cells_covering(&self, centre: Vec2, radius: f64) -> impl Iterator<Item = usize> {
+        let (columns, rows) = (self.columns as isize, self.rows as isize);
+        let (x0, x1) = cell_span(
+            centre.x - radius,
+            centre.x + radius,
+            self.cell_width,
+            self.columns,
+            self.wraps,
+        );
+        let (y0, y1) = cell_span(
+            centre.y - radius,
+            centre.y + radius,
+            self.cell_height,
+            self.rows,
+            self.wraps,
+        );
+        return (y0..=y1).flat_map(move |y| {
+            (x0..=x1).map(move |x| (y.rem_euclid(rows) * columns + x.rem_euclid(columns)) as usize)
+        });
+    }
+
+    pub fn insert(&mut self, index: usize, centre: Vec2, radius: f64) {
+        for cell in self.cells_covering(centre, radius) {
+            self.cells[cell].push(index);
+        }
+    }
+
+    // Replaces the contents of `found` with everything sharing a cell with
+    // the circle, in order of index and without repeats.
+    pub fn query(&self, centre: Vec2, radius: f64, found: &mut Vec<usize>) {
+        found.clear();
+        for cell in self.cells_covering(centre, radius) {
+            found.extend_from_slice(&self.cells[cell]);
+        }
+        found.sort_unstable();
+        found.dedup();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounds::TEST_BOUNDS;
+    use expectest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn found(grid: &SpatialGrid, centre: Vec2, radius: f64) -> Vec<usize> {
+        let mut found = Vec::new();
+        grid.query(centre, radius, &mut found);
+        return found;
+    }
+
+    #[test]
+    fn test_query_finds_neighbours() {
+        let mut grid = SpatialGrid::new(&TEST_BOUNDS, 32.0);
+        grid.insert(0, Vec2::new(50.0, 50.0), 5.0);
+        grid.insert(1, Vec2::new(150.0, 150.0), 5.0);
+        grid.insert(2, Vec2::new(60.0, 55.0), 20.0);
+        expect!(found(&grid, Vec2::new(52.0, 52.0), 1.0)).to(be_equal_to(vec![0, 2]));
+        expect!(found(&grid, Vec2::new(148.0, 150.0), 1.0)).to(be_equal_to(vec![1]));
+        expect!(found(&grid, Vec2::new(100.0, 10.0), 1.0)).to(be_equal_to(vec![]));
+        grid.clear();
+        expect!(found(&grid, Vec2::new(52.0, 52.0), 1.0)).to(be_equal_to(vec![]));
+    }
+
+    #[test]
+    fn test_query_wraps() {
+        let mut grid = SpatialGrid::new(&TEST_BOUNDS, 32.0);
+        grid.insert(0, Vec2::new(2.0, 2.0), 5.0);
+        expect!(found(&grid, Vec2::new(198.0, 100.0), 1.0)).to(be_equal_to(vec![]));
+        expect!(found(&grid, Vec2::new(198.0, 198.0), 1.0)).to(be_equal_to(vec![0]));
+        expect!(found(&grid, Vec2::new(198.0, 2.0), 1.0)).to(be_equal_to(vec![0]));
+        expect!(found(&grid, Vec2::new(2.0, 198.0), 1.0)).to(be_equal_to(vec![0]));
+        // Bigger than the whole arena
+        expect!(found(&grid, Vec2::new(100.0, 100.0), 500.0)).to(be_equal_to(vec![0]));
+    }
+
+    #[test]
+    fn test_query_clamps_without_wrapping() {
+        let bounds = Bounds::new(200.0, 200.0, Boundary::Bounce);
+        let mut grid = SpatialGrid::new(&bounds, 32.0);
+        grid.insert(0, Vec2::new(2.0, 2.0), 5.0);
+        grid.insert(1, Vec2::new(-50.0, 100.0), 5.0);
+        expect!(found(&grid, Vec2::new(198.0, 198.0), 1.0)).to(be_equal_to(vec![]));
+        expect!(found(&grid, Vec2::new(1.0, 100.0), 1.0)).to(be_equal_to(vec![1]));
+    }
+
+    // Thousands of small circles spread over a big arena each share cells
+    // with only a handful of others, rather than with all of them
+    #[test]
+    fn test_query_scales() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let bounds = Bounds::new(3200.0, 3200.0, Boundary::Wrap);
+        let mut grid = SpatialGrid::new(&bounds, 32.0);
+        let circles: Vec<(Vec2, f64)> = (0..5000)
+            .map(|_| {
+                let centre = Vec2::new(
+                    rng.gen_range(0.0..bounds.width),
+                    rng.gen_range(0.0..bounds.height),
+                );
+                (centre, rng.gen_range(1.0..10.0))
+            })
+            .collect();
+        for (i, &(centre, radius)) in circles.iter().enumerate() {
+            grid.insert(i, centre, radius);
+        }
+        let mut candidates = Vec::new();
+        let mut pairs = 0;
+        for &(centre, radius) in circles.iter() {
+            grid.query(centre, radius, &mut candidates);
+            pairs += candidates.len();
+        }
+        assert!(pairs < circles.len() * 10, "{} pairs", pairs);
+    }
+
+    #[test]
+    fn test_query_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(36);
+        for bounds in [TEST_BOUNDS, Bounds::new(230.0, 170.0, Boundary::Wrap)] {
+            let mut grid = SpatialGrid::new(&bounds, 24.0);
+            let circles: Vec<(Vec2, f64)> = (0..200)
+                .map(|_| {
+                    let centre = Vec2::new(
+                        rng.gen_range(0.0..bounds.width),
+                        rng.gen_range(0.0..bounds.height),
+                    );
+                    (centre, rng.gen_range(1.0..30.0))
+                })
+                .collect();
+            for (i, &(centre, radius)) in circles.iter().enumerate() {
+                grid.insert(i, centre, radius);
+            }
+            let mut candidates = Vec::new();
+            for &(centre, radius) in circles.iter() {
+                grid.query(centre, radius, &mut candidates);
+                for (j, &(other, other_radius)) in circles.iter().enumerate() {
+                    if circles_overlap(&bounds, centre, radius, other, other_radius) {
+                        assert!(candidates.contains(&j));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::geometry::Vec2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HandlingModel {
+    // Thrust changes velocity and nothing else does
+    Newtonian,
+    // Friction slowly brings the ship to a stop
+    Arcade,
+    // The ship counter-thrusts on its own to kill any drift
+    FlightAssist,
+}
+
+impl HandlingModel {
+    pub fn from_name(name: &str) -> Option<HandlingModel> {
+        return match name {
+            "newtonian" => Some(HandlingModel::Newtonian),
+            "arcade" => Some(HandlingModel::Arcade),
+            "assist" => Some(HandlingModel::FlightAssist),
+            _ => None,
+        };
+    }
+}
+
+// How the ship responds to the controls
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Handling {
+    pub model: HandlingModel,
+    // Acceleration at full thrust
+    pub thrust: f64,
+    // Fraction of the ship's speed lost per second
+    pub drag: f64,
+    pub max_speed: f64,
+    // Radians per second at full turn
+    pub turn_rate: f64,
+    // How quickly the ship reaches its turn rate, in radians per second squared
+    pub angular_accel: f64,
+}
+
+// Values given on the command line in place of the preset's own
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Tuning {
+    pub thrust: Option<f64>,
+    pub drag: Option<f64>,
+    pub max_speed: Option<f64>,
+    pub turn_rate: Option<f64>,
+    pub angular_accel: Option<f64>,
+}
+
+impl Tuning {
+    pub fn apply(&self, handling: Handling) -> Handling {
+        return Handling {
+            model: handling.model,
+            thrust: self.thrust.unwrap_or(handling.thrust),
+            drag: self.drag.unwrap_or(handling.drag),
+            max_speed: self.max_speed.unwrap_or(handling.max_speed),
+            turn_rate: self.turn_rate.unwrap_or(handling.turn_rate),
+            angular_accel: self.angular_accel.unwrap_or(handling.angular_accel),
+        };
+    }
+}
+
+// Changes v by up to max_change toward target
+fn approach(v: Vec2, target: Vec2, max_change: f64) -> Vec2 {
+    let d = target - v;
+    let distance = d.length();
+    if distance <= max_change {
+        return target;
+    }
+    return v + d * (max_change / distance);
+}
+
+impl Handling {
+    // The ship's velocity after dt, given the thrust input from -1 (full
+    // reverse) to 1 (full ahead).
+    pub fn thrust(&self, velocity: Vec2, heading: f64, input: f64, dt: f64) -> Vec2 {
+        let ahead = Vec2::from_heading(heading, 1.0);
+        let mut velocity = velocity + ahead * (input * self.thrust * dt);
+        match self.model {
+            HandlingModel::Newtonian => (),
+            HandlingModel::Arcade => velocity = velocity * (1.0 - self.drag).powf(dt),
+            HandlingModel::FlightAssist => {
+                // With the throttle open only sideways drift is cancelled,
+                // otherwise the ship brakes to a stop.
+                let target = if input == 0.0 {
+                    Vec2::zero()
+                } else {
+                    ahead * velocity.dot(ahead)
+                };
+                velocity = approach(velocity, target, self.thrust * dt);
+            }
+        }
+        let speed = velocity.length();
+        if speed > self.max_speed {
+            velocity = velocity * (self.max_speed / speed);
+        }
+        return velocity;
+    }
+
+    // The ship's rate of turn after dt, given the turn input from -1 (full
+    // left) to 1 (full right). An infinite angular acceleration turns at the
+    // new rate straight away.
+    pub fn spin(&self, spin: f64, input: f64, dt: f64) -> f64 {
+        let target = input * self.turn_rate;
+        if dt <= 0.0 {
+            return spin;
+        }
+        if self.angular_accel.is_infinite() {
+            return target;
+        }
+        let max_change = self.angular_accel * dt;
+        return spin + (target - spin).clamp(-max_change, max_change);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+    use std::f64::consts::PI;
+
+    fn handling(model: HandlingModel) -> Handling {
+        return Handling {
+            model: model,
+            thrust: 100.0,
+            drag: 0.5,
+            max_speed: 200.0,
+            turn_rate: 5.0,
+            angular_accel: 10.0,
+        };
+    }
+
+    #[test]
+    fn test_newtonian_coasts() {
+        let h = handling(HandlingModel::Newtonian);
+        let v = h.thrust(Vec2::zero(), 0.0, 1.0, 0.5);
+        expect!(v.y).to(be_close_to(-50.0));
+        let coasting = h.thrust(v, PI / 2.0, 0.0, 10.0);
+        expect!(coasting).to(be_equal_to(v));
+    }
+
+    #[test]
+    fn test_max_speed() {
+        let h = handling(HandlingModel::Newtonian);
+        let v = h.thrust(Vec2::new(190.0, 0.0), PI / 2.0, 1.0, 1.0);
+        expect!(v.x).to(be_close_to(200.0));
+    }
+
+    #[test]
+    fn test_arcade_drag() {
+        let h = handling(HandlingModel::Arcade);
+        let v = h.thrust(Vec2::new(100.0, 0.0), 0.0, 0.0, 1.0);
+        expect!(v.x).to(be_close_to(50.0));
+        let v = h.thrust(v, 0.0, 0.0, 2.0);
+        expect!(v.x).to(be_close_to(12.5));
+    }
+
+    #[test]
+    fn test_flight_assist_kills_drift() {
+        let h = handling(HandlingModel::FlightAssist);
+        // Braking with the throttle closed
+        let v = h.thrust(Vec2::new(30.0, 40.0), 0.0, 0.0, 0.25);
+        expect!(v.length()).to(be_close_to(25.0));
+        let v = h.thrust(v, 0.0, 0.0, 1.0);
+        expect!(v).to(be_equal_to(Vec2::zero()));
+        // Sideways drift goes away while thrusting ahead
+        let v = h.thrust(Vec2::new(20.0, 0.0), 0.0, 1.0, 0.5);
+        expect!(v.x).to(be_close_to(0.0));
+        expect!(v.y).to(be_close_to(-50.0));
+    }
+
+    #[test]
+    fn test_spin() {
+        let h = handling(HandlingModel::Newtonian);
+        expect!(h.spin(0.0, 1.0, 0.1)).to(be_close_to(1.0));
+        expect!(h.spin(4.5, 1.0, 0.1)).to(be_close_to(5.0));
+        expect!(h.spin(5.0, 0.0, 0.1)).to(be_close_to(4.0));
+        expect!(h.spin(0.0, -1.0, 1.0)).to(be_close_to(-5.0));
+        let instant = Handling {
+            angular_accel: f64::INFINITY,
+            ..h
+        };
+        expect!(instant.spin(0.0, 1.0, 0.01)).to(be_close_to(5.0));
+        // No time passing doesn't change anything, however quick the ship is
+        expect!(instant.spin(2.0, 1.0, 0.0)).to(be_equal_to(2.0));
+        expect!(h.spin(2.0, -1.0, 0.0)).to(be_equal_to(2.0));
+    }
+
+    #[test]
+    fn test_tuning() {
+        let tuning = Tuning {
+            thrust: Some(300.0),
+            angular_accel: Some(f64::INFINITY),
+            ..Tuning::default()
+        };
+        let tuned = tuning.apply(handling(HandlingModel::Arcade));
+        expect!(tuned.model).to(be_equal_to(HandlingModel::Arcade));
+        expect!(tuned.thrust).to(be_equal_to(300.0));
+        expect!(tuned.drag).to(be_equal_to(0.5));
+        expect!(tuned.angular_accel).to(be_equal_to(f64::INFINITY));
+        expect!(Tuning::default().apply(tuned)).to(be_equal_to(tuned));
+    }
+}
@@ -1,98 +1,699 @@
-use crate::point::Point;
+use crate::geometry::{Segment, Vec2};
 
-// Intersection of p, p+r and q, q+s
-// For details, See: http://stackoverflow.com/questions/563198/how-do-you-detect-where-two-line-segments-intersect
-// p + t r = q + u s
-// t = ((q − p) × s) / (r × s)
-// u = ((q − p) × r) / (r × s)
+use num::rational::BigRational;
+use num::Signed;
+use std::cmp::Ordering;
 
-pub fn lines_intersect(l1: [f64; 4], l2: [f64; 4]) -> bool {
-    let p = Point::new(l1[0], l1[1]);
-    let q = Point::new(l2[0], l2[1]);
-    let r = Point::new(l1[2] - l1[0], l1[3] - l1[1]);
-    let s = Point::new(l2[2] - l2[0], l2[3] - l2[1]);
-
-    // If r × s = 0 and (q − p) × r = 0, then the two lines are collinear.
-    if r.cross(s) == 0.0 && (p - q).cross(r) == 0.0 {
-        // If the interval between t0 and t1 intersects the interval [0, 1] then the line segments are collinear and overlapping; otherwise they are collinear and disjoint.
-        // Note that if s and r point in opposite directions, then s · r < 0 and so the interval to be checked is [t1, t0] rather than [t0, t1].
-        // t0 = (q − p) · r / (r · r)
-        // t1 = (q + s − p) · r / (r · r) = t0 + s · r / (r · r)
-        let t0 = (q - p).dot(r / r.dot(r));
-        let t1 = t0 + s.dot(r / r.dot(r));
-        return !(t0 < 0.0 && t1 < 0.0 || t0 > 1.0 && t1 > 1.0);
+// Which side of the line through a and b the point c is on: Greater if
+// (b - a) × (c - a) is positive, Less if it's negative, and Equal if the
+// three points are collinear. The answer is exact, so it never depends on
+// rounding. The float calculation is used whenever its error bound shows it
+// has the sign right, and only close calls are redone, a bit more carefully
+// each time, until the sign is certain.
+// See: https://www.cs.cmu.edu/~quake/robust.html
+pub fn orientation(a: Vec2, b: Vec2, c: Vec2) -> Ordering {
+    let left = (a.x - c.x) * (b.y - c.y);
+    let right = (a.y - c.y) * (b.x - c.x);
+    let det = left - right;
+    // Rounding never changes the sign of a difference or a product, so when
+    // the two products have different signs, or either is zero, the sign of
+    // the result is already right.
+    if (left >= 0.0 && right <= 0.0) || (left <= 0.0 && right >= 0.0) {
+        return det.partial_cmp(&0.0).unwrap_or(Ordering::Equal);
+    }
+    let error_bound = ORIENTATION_ERROR * (left.abs() + right.abs());
+    if det > error_bound {
+        return Ordering::Greater;
+    } else if -det > error_bound {
+        return Ordering::Less;
     }
+    return adaptive_orientation(a, b, c, left.abs() + right.abs());
+}
 
-    // If r × s = 0 and (q − p) × r ≠ 0, then the two lines are parallel and non-intersecting.
-    if r.cross(s) == 0.0 {
-        return false;
+// Relative error of the float determinant, from Shewchuk's ccwerrboundA
+const ORIENTATION_ERROR: f64 = (3.0 + 16.0 * HALF_EPSILON) * HALF_EPSILON;
+// And of the later stages, from ccwerrboundB, ccwerrboundC and resulterrbound
+const ORIENTATION_ERROR_B: f64 = (2.0 + 12.0 * HALF_EPSILON) * HALF_EPSILON;
+const ORIENTATION_ERROR_C: f64 = (9.0 + 64.0 * HALF_EPSILON) * HALF_EPSILON * HALF_EPSILON;
+const RESULT_ERROR: f64 = (3.0 + 8.0 * HALF_EPSILON) * HALF_EPSILON;
+const HALF_EPSILON: f64 = f64::EPSILON / 2.0;
+
+// Shewchuk's orient2dadapt. First the determinant of the rounded differences
+// is worked out exactly, then the bits rounded off the differences are
+// brought back in, roughly and then exactly, stopping as soon as the sign is
+// certain. Everything stays in floats on the stack, so the game can check
+// close calls every tick without allocating.
+fn adaptive_orientation(a: Vec2, b: Vec2, c: Vec2, det_sum: f64) -> Ordering {
+    let points = [a.x, a.y, b.x, b.y, c.x, c.y];
+    if !points
+        .iter()
+        .all(|&x| x == 0.0 || (SMALLEST..=LARGEST).contains(&x.abs()))
+    {
+        return exact_orientation(a, b, c);
+    }
+    let (acx, acy) = (a.x - c.x, a.y - c.y);
+    let (bcx, bcy) = (b.x - c.x, b.y - c.y);
+    let mut det = Expansion::new();
+    det.add_product(acx, bcy);
+    det.add_product(-acy, bcx);
+    let estimate = det.estimate();
+    let error_bound = ORIENTATION_ERROR_B * det_sum;
+    if estimate >= error_bound || -estimate >= error_bound {
+        return det.sign();
     }
 
-    // t = (q − p) × s / (r × s)
-    // u = (p − q) × r / (s × r)
-    let t = (q - p).cross(s / (r.cross(s)));
-    let u = (p - q).cross(r / (s.cross(r)));
+    let (acx_tail, acy_tail) = (diff_tail(a.x, c.x, acx), diff_tail(a.y, c.y, acy));
+    let (bcx_tail, bcy_tail) = (diff_tail(b.x, c.x, bcx), diff_tail(b.y, c.y, bcy));
+    if acx_tail == 0.0 && acy_tail == 0.0 && bcx_tail == 0.0 && bcy_tail == 0.0 {
+        // The differences were exact, so the determinant already is
+        return det.sign();
+    }
+    let error_bound = ORIENTATION_ERROR_C * det_sum + RESULT_ERROR * estimate.abs();
+    let estimate = estimate + (acx * bcy_tail + bcy * acx_tail) - (acy * bcx_tail + bcx * acy_tail);
+    if estimate >= error_bound || -estimate >= error_bound {
+        return estimate.partial_cmp(&0.0).unwrap_or(Ordering::Equal);
+    }
 
-    // If r × s ≠ 0 and 0 ≤ t ≤ 1 and 0 ≤ u ≤ 1, the two line segments meet at the point p + t r = q + u s.
-    return 0.0 <= t && t <= 1.0 && 0.0 <= u && u <= 1.0;
+    det.add_product(acx, bcy_tail);
+    det.add_product(acx_tail, bcy);
+    det.add_product(acx_tail, bcy_tail);
+    det.add_product(-acy, bcx_tail);
+    det.add_product(-acy_tail, bcx);
+    det.add_product(-acy_tail, bcx_tail);
+    return det.sign();
+}
+
+// The float stages are only exact while nothing overflows or gets too small
+// to keep all its bits, which points anywhere near the arena never do
+const SMALLEST: f64 = 1e-90;
+const LARGEST: f64 = 1e90;
+
+// A number kept exactly as a sum of floats, smallest first, none of which
+// overlap, so its sign is the sign of the largest. Two products for the
+// determinant and six more for the tails never need more than 16.
+struct Expansion {
+    terms: [f64; 16],
+    len: usize,
+}
+
+impl Expansion {
+    fn new() -> Expansion {
+        return Expansion {
+            terms: [0.0; 16],
+            len: 0,
+        };
+    }
+
+    // Shewchuk's Grow-Expansion, dropping any zeros
+    fn add(&mut self, x: f64) {
+        let mut sum = x;
+        let mut len = 0;
+        for i in 0..self.len {
+            let (total, error) = two_sum(sum, self.terms[i]);
+            if error != 0.0 {
+                self.terms[len] = error;
+                len += 1;
+            }
+            sum = total;
+        }
+        if sum != 0.0 {
+            self.terms[len] = sum;
+            len += 1;
+        }
+        self.len = len;
+    }
+
+    fn add_product(&mut self, a: f64, b: f64) {
+        let product = a * b;
+        // A fused multiply-add rounds only once, so this is exactly what
+        // rounding took off the product
+        self.add(a.mul_add(b, -product));
+        self.add(product);
+    }
+
+    fn estimate(&self) -> f64 {
+        return self.terms[..self.len].iter().sum();
+    }
+
+    fn sign(&self) -> Ordering {
+        if self.len == 0 {
+            return Ordering::Equal;
+        }
+        return self.terms[self.len - 1]
+            .partial_cmp(&0.0)
+            .unwrap_or(Ordering::Equal);
+    }
+}
+
+// a + b, and exactly what rounding took off it
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    return (sum, (a - a_virtual) + (b - b_virtual));
+}
+
+// Exactly what rounding took off diff = a - b
+fn diff_tail(a: f64, b: f64, diff: f64) -> f64 {
+    let b_virtual = a - diff;
+    let a_virtual = diff + b_virtual;
+    return (a - a_virtual) + (b_virtual - b);
+}
+
+// Every finite float is a fraction with a power of two below, so this is
+// exact. Points at infinity or NaN count as collinear. It allocates, but only
+// points too far out or too fine for the float stages ever get here.
+fn exact_orientation(a: Vec2, b: Vec2, c: Vec2) -> Ordering {
+    let exact = |x: f64| BigRational::from_float(x);
+    let (Some(ax), Some(ay), Some(bx), Some(by), Some(cx), Some(cy)) = (
+        exact(a.x),
+        exact(a.y),
+        exact(b.x),
+        exact(b.y),
+        exact(c.x),
+        exact(c.y),
+    ) else {
+        return Ordering::Equal;
+    };
+    let det = (&ax - &cx) * (&by - &cy) - (&ay - &cy) * (&bx - &cx);
+    if det.is_positive() {
+        return Ordering::Greater;
+    } else if det.is_negative() {
+        return Ordering::Less;
+    }
+    return Ordering::Equal;
+}
+
+// Whether c, already known to be collinear with a and b, is between them
+fn on_segment(a: Vec2, b: Vec2, c: Vec2) -> bool {
+    return a.x.min(b.x) <= c.x
+        && c.x <= a.x.max(b.x)
+        && a.y.min(b.y) <= c.y
+        && c.y <= a.y.max(b.y);
+}
+
+// Whether two segments touch, including at their ends or by overlapping
+// along the same line. Built only on `orientation` and comparisons, so it's
+// exact: the same segments always give the same answer, either way round.
+pub fn lines_intersect(l1: Segment, l2: Segment) -> bool {
+    let (p1, p2) = (l1.start, l1.end);
+    let (q1, q2) = (l2.start, l2.end);
+    let (o1, o2) = (orientation(p1, p2, q1), orientation(p1, p2, q2));
+    let (o3, o4) = (orientation(q1, q2, p1), orientation(q1, q2, p2));
+
+    // Each segment has the other's ends on opposite sides of it, or one end
+    // exactly on it.
+    if o1 != o2 && o3 != o4 {
+        return true;
+    }
+    // Otherwise they can only touch where an end lies on the other segment
+    return (o1 == Ordering::Equal && on_segment(p1, p2, q1))
+        || (o2 == Ordering::Equal && on_segment(p1, p2, q2))
+        || (o3 == Ordering::Equal && on_segment(q1, q2, p1))
+        || (o4 == Ordering::Equal && on_segment(q1, q2, p2));
 }
 
 #[cfg(test)]
 #[test]
 fn test_lines_intersect() {
-    let line = [-1.0, -1.0, 1.0, 1.0];
+    let line = Segment::from([-1.0, -1.0, 1.0, 1.0]);
     // Two lines intersect normally
-    assert!(lines_intersect(line, [1.0, -1.0, -1.0, 1.0]));
+    assert!(lines_intersect(line, Segment::from([1.0, -1.0, -1.0, 1.0])));
     // Two lines don't intersect at all
-    assert!(!lines_intersect(line, [-2.0, -2.0, -3.0, -3.0]));
+    assert!(!lines_intersect(
+        line,
+        Segment::from([-2.0, -2.0, -3.0, -3.0])
+    ));
     // Two lines are parallel and don't intersect
-    assert!(!lines_intersect(line, [-1.0, 0.0, 1.0, 2.0]));
+    assert!(!lines_intersect(line, Segment::from([-1.0, 0.0, 1.0, 2.0])));
     // Two lines are co-linear and overlapping
-    assert!(lines_intersect(line, [-1.5, -1.5, 0.5, 0.5]));
-    assert!(lines_intersect(line, [0.5, 0.5, 1.5, 1.5]));
+    assert!(lines_intersect(line, Segment::from([-1.5, -1.5, 0.5, 0.5])));
+    assert!(lines_intersect(line, Segment::from([0.5, 0.5, 1.5, 1.5])));
     // Two lines are co-linear and one contains the other
-    assert!(lines_intersect(line, [-2.0, -2.0, 2.0, 2.0]));
-    assert!(lines_intersect(line, [-0.5, -0.5, 0.5, 0.5]));
+    assert!(lines_intersect(line, Segment::from([-2.0, -2.0, 2.0, 2.0])));
+    assert!(lines_intersect(line, Segment::from([-0.5, -0.5, 0.5, 0.5])));
     // Two lines are co-linear and non-overlapping
-    assert!(!lines_intersect(line, [-3.0, -3.0, -2.0, -2.0]));
-    assert!(!lines_intersect(line, [2.0, 2.0, 3.0, 3.0]));
+    assert!(!lines_intersect(
+        line,
+        Segment::from([-3.0, -3.0, -2.0, -2.0])
+    ));
+    assert!(!lines_intersect(line, Segment::from([2.0, 2.0, 3.0, 3.0])));
     // Two lines are the same
     assert!(lines_intersect(line, line));
     // Two lines intersect at one point
-    assert!(lines_intersect(line, [1.0, 1.0, 2.0, 2.0]));
-    assert!(lines_intersect(line, [1.0, 1.0, 1.0, 2.0]));
+    assert!(lines_intersect(line, Segment::from([1.0, 1.0, 2.0, 2.0])));
+    assert!(lines_intersect(line, Segment::from([1.0, 1.0, 1.0, 2.0])));
     // One line's point touches the other line
-    assert!(lines_intersect(line, [0.0, 2.0, 2.0, 0.0]));
+    assert!(lines_intersect(line, Segment::from([0.0, 2.0, 2.0, 0.0])));
+}
+
+// How far along l1, from 0 at its start to 1 at its end, it first touches l2.
+// Whether they touch at all is decided exactly by `lines_intersect`; only
+// where is left to floats.
+//
+// Intersection of p, p+r and q, q+s
+// For details, See: http://stackoverflow.com/questions/563198/how-do-you-detect-where-two-line-segments-intersect
+// p + t r = q + u s
+// t = ((q − p) × s) / (r × s)
+// u = ((q − p) × r) / (r × s)
+pub fn intersection_time(l1: Segment, l2: Segment) -> Option<f64> {
+    let (p, p2) = (l1.start, l1.end);
+    let (q, q2) = (l2.start, l2.end);
+    let r = p2 - p;
+    let s = q2 - q;
+
+    if r.dot(r) == 0.0 || !lines_intersect(l1, l2) {
+        return None;
+    }
+
+    if orientation(p, p2, q) == Ordering::Equal && orientation(p, p2, q2) == Ordering::Equal {
+        // Collinear, so l1 first touches l2 at whichever end of l2 it reaches first.
+        let t0 = (q - p).dot(r / r.dot(r));
+        let t1 = t0 + s.dot(r / r.dot(r));
+        return Some(t0.min(t1).clamp(0.0, 1.0));
+    }
+
+    if r.cross(s) == 0.0 {
+        // Too close to parallel for the float calculation, but they do touch,
+        // most likely where an end of one lies on the other.
+        let along = |end: Vec2| (end - p).dot(r) / r.dot(r);
+        let touching = |a: Vec2, b: Vec2, end: Vec2| {
+            orientation(a, b, end) == Ordering::Equal && on_segment(a, b, end)
+        };
+        let t = [(p, 0.0), (p2, 1.0)]
+            .into_iter()
+            .filter(|&(end, _)| touching(q, q2, end))
+            .map(|(_, t)| t)
+            .chain(
+                [q, q2]
+                    .into_iter()
+                    .filter(|&end| touching(p, p2, end))
+                    .map(along),
+            )
+            .fold(None, |earliest: Option<f64>, t| {
+                Some(earliest.map_or(t, |e| e.min(t)))
+            });
+        return Some(t.unwrap_or_else(|| along(q)).clamp(0.0, 1.0));
+    }
+
+    let t = (q - p).cross(s / (r.cross(s)));
+    return Some(t.clamp(0.0, 1.0));
+}
+
+// The earliest time a path touches any of the edges
+pub fn earliest_intersection<I: Iterator<Item = Segment>>(path: Segment, edges: I) -> Option<f64> {
+    return edges
+        .filter_map(|edge| intersection_time(path, edge))
+        .fold(None, |earliest: Option<f64>, t| {
+            Some(earliest.map_or(t, |e| e.min(t)))
+        });
 }
 
-fn ray(point: Point) -> [f64; 4] {
-    let Point { x, y } = point;
-    return [-100000.0, y, x, y];
+#[cfg(test)]
+#[test]
+fn test_intersection_time() {
+    let line = Segment::from([-1.0, -1.0, 1.0, 1.0]);
+    assert_eq!(
+        intersection_time(line, Segment::from([1.0, -1.0, -1.0, 1.0])),
+        Some(0.5)
+    );
+    assert_eq!(
+        intersection_time(line, Segment::from([-2.0, -2.0, -3.0, -3.0])),
+        None
+    );
+    assert_eq!(
+        intersection_time(line, Segment::from([-1.0, 0.0, 1.0, 2.0])),
+        None
+    );
+    // Co-linear and overlapping from either direction
+    assert_eq!(
+        intersection_time(line, Segment::from([0.0, 0.0, 2.0, 2.0])),
+        Some(0.5)
+    );
+    assert_eq!(
+        intersection_time(line, Segment::from([2.0, 2.0, 0.0, 0.0])),
+        Some(0.5)
+    );
+    assert_eq!(
+        intersection_time(line, Segment::from([-2.0, -2.0, 2.0, 2.0])),
+        Some(0.0)
+    );
+    // Touching at the far end
+    assert_eq!(
+        intersection_time(line, Segment::from([1.0, 1.0, 1.0, 2.0])),
+        Some(1.0)
+    );
+    // A path that doesn't go anywhere
+    assert_eq!(
+        intersection_time(Segment::from([0.0, 0.0, 0.0, 0.0]), line),
+        None
+    );
+    let edges = [
+        Segment::from([0.5, -1.0, 0.5, 1.0]),
+        Segment::from([0.0, -1.0, 0.0, 1.0]),
+    ];
+    assert_eq!(
+        earliest_intersection(Segment::from([-1.0, 0.0, 1.0, 0.0]), edges.into_iter()),
+        Some(0.5)
+    );
+    assert_eq!(
+        earliest_intersection(Segment::from([-1.0, 0.0, -0.5, 0.0]), edges.into_iter()),
+        None
+    );
 }
 
-pub fn point_in<I: Iterator<Item = [f64; 4]>>(point: Point, edges: I) -> bool {
-    let r = ray(point);
-    return edges.fold(false, |inside, edge| inside ^ lines_intersect(r, edge));
+// Where a point is relative to a polygon
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Containment {
+    Outside,
+    // On one of the edges or corners
+    Boundary,
+    Inside,
+}
+
+// Whether a point is inside a closed outline, by counting how many times the
+// outline winds around it. Each edge only counts when it crosses the point's
+// row going up or going down, including its lower end but not its upper one,
+// so a corner on that row is counted once and flat edges not at all.
+// See: https://en.wikipedia.org/wiki/Point_in_polygon#Winding_number_algorithm
+pub fn containment<I: Iterator<Item = Segment>>(point: Vec2, edges: I) -> Containment {
+    let mut winding = 0;
+    for edge in edges {
+        let (a, b) = (edge.start, edge.end);
+        let side = orientation(a, b, point);
+        if side == Ordering::Equal && on_segment(a, b, point) {
+            return Containment::Boundary;
+        }
+        if a.y <= point.y {
+            if b.y > point.y && side == Ordering::Greater {
+                winding += 1;
+            }
+        } else if b.y <= point.y && side == Ordering::Less {
+            winding -= 1;
+        }
+    }
+    if winding != 0 {
+        return Containment::Inside;
+    }
+    return Containment::Outside;
+}
+
+// Whether a point is inside a closed outline or on its boundary
+pub fn point_in<I: Iterator<Item = Segment>>(point: Vec2, edges: I) -> bool {
+    return containment(point, edges) != Containment::Outside;
 }
 
 #[cfg(test)]
 #[test]
-#[ignore]
 fn test_point_in() {
-    let box_points = vec![
-        [-1.0, 0.0, 0.0, 1.0],
-        [0.0, 1.0, 1.0, 0.0],
-        [1.0, 0.0, 0.0, -1.0],
-        [0.0, -1.0, -1.0, 0.0],
+    let box_points = [
+        Segment::from([-1.0, 0.0, 0.0, 1.0]),
+        Segment::from([0.0, 1.0, 1.0, 0.0]),
+        Segment::from([1.0, 0.0, 0.0, -1.0]),
+        Segment::from([0.0, -1.0, -1.0, 0.0]),
     ];
     let points = || box_points.iter().cloned();
     // Point outside the box
-    assert!(!point_in(Point::new(-2.0, 0.0), points()));
-    assert!(!point_in(Point::new(2.0, 0.0), points()));
+    assert!(!point_in(Vec2::new(-2.0, 0.0), points()));
+    assert!(!point_in(Vec2::new(2.0, 0.0), points()));
     // Point inside the box
-    assert!(point_in(Point::new(0.0, 0.0), points()));
+    assert!(point_in(Vec2::zero(), points()));
     // Point touching the box
-    assert!(point_in(Point::new(-1.0, 0.0), points()));
-    assert!(point_in(Point::new(1.0, 0.0), points()));
-    assert!(point_in(Point::new(-0.5, -0.5), points()));
+    assert!(point_in(Vec2::new(-1.0, 0.0), points()));
+    assert!(point_in(Vec2::new(1.0, 0.0), points()));
+    assert!(point_in(Vec2::new(-0.5, -0.5), points()));
+}
+
+// The point on the segment nearest to p
+pub fn closest_point(p: Vec2, segment: Segment) -> Vec2 {
+    let along = segment.along();
+    let length_squared = along.dot(along);
+    if length_squared == 0.0 {
+        return segment.start;
+    }
+    let t = (p - segment.start).dot(along) / length_squared;
+    return segment.at(t.clamp(0.0, 1.0));
+}
+
+// Shortest distance from p to anywhere on the segment
+pub fn point_segment_distance(p: Vec2, segment: Segment) -> f64 {
+    return (p - closest_point(p, segment)).length();
+}
+
+// Shortest distance between two segments. Segments that don't touch are
+// closest at an end of one of them.
+pub fn segment_distance(l1: Segment, l2: Segment) -> f64 {
+    if lines_intersect(l1, l2) {
+        return 0.0;
+    }
+    return point_segment_distance(l1.start, l2)
+        .min(point_segment_distance(l1.end, l2))
+        .min(point_segment_distance(l2.start, l1))
+        .min(point_segment_distance(l2.end, l1));
+}
+
+// How far a point is from a closed outline, or 0 if it's inside or on it
+pub fn point_polygon_distance<I: Iterator<Item = Segment> + Clone>(point: Vec2, edges: I) -> f64 {
+    if point_in(point, edges.clone()) {
+        return 0.0;
+    }
+    return edges
+        .map(|edge| point_segment_distance(point, edge))
+        .fold(f64::INFINITY, f64::min);
+}
+
+#[cfg(test)]
+#[test]
+fn test_distances() {
+    let line = Segment::from([0.0, 0.0, 4.0, 0.0]);
+    // Beside the segment, and past either end
+    assert_eq!(
+        closest_point(Vec2::new(1.0, 3.0), line),
+        Vec2::new(1.0, 0.0)
+    );
+    assert_eq!(
+        closest_point(Vec2::new(-3.0, 4.0), line),
+        Vec2::new(0.0, 0.0)
+    );
+    assert_eq!(point_segment_distance(Vec2::new(7.0, 4.0), line), 5.0);
+    // A segment with no length is a point
+    let dot = Segment::from([1.0, 1.0, 1.0, 1.0]);
+    assert_eq!(closest_point(Vec2::new(4.0, 5.0), dot), Vec2::new(1.0, 1.0));
+    // Crossing, parallel, and end to end
+    assert_eq!(
+        segment_distance(line, Segment::from([2.0, -1.0, 2.0, 1.0])),
+        0.0
+    );
+    assert_eq!(
+        segment_distance(line, Segment::from([1.0, 2.0, 3.0, 2.0])),
+        2.0
+    );
+    assert_eq!(
+        segment_distance(line, Segment::from([7.0, 4.0, 9.0, 4.0])),
+        5.0
+    );
+    // An end nearest the middle of the other segment
+    assert_eq!(
+        segment_distance(Segment::from([2.0, 1.0, 2.0, 5.0]), line),
+        1.0
+    );
+    let square = [
+        Segment::from([0.0, 0.0, 2.0, 0.0]),
+        Segment::from([2.0, 0.0, 2.0, 2.0]),
+        Segment::from([2.0, 2.0, 0.0, 2.0]),
+        Segment::from([0.0, 2.0, 0.0, 0.0]),
+    ];
+    let distance = |x: f64, y: f64| point_polygon_distance(Vec2::new(x, y), square.into_iter());
+    assert_eq!(distance(1.0, 1.0), 0.0);
+    assert_eq!(distance(2.0, 1.0), 0.0);
+    assert_eq!(distance(5.0, 1.0), 3.0);
+    assert_eq!(distance(5.0, 6.0), 5.0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::Polygon;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f64::consts::PI;
+
+    fn naive_orientation(a: Vec2, b: Vec2, c: Vec2) -> Ordering {
+        return (b - a).cross(c - a).partial_cmp(&0.0).unwrap();
+    }
+
+    // Nudges x by a number of the smallest steps a float can take
+    fn nudge(x: f64, steps: u64) -> f64 {
+        return f64::from_bits(x.to_bits() + steps);
+    }
+
+    #[test]
+    fn test_orientation() {
+        let (a, b) = (Vec2::zero(), Vec2::new(2.0, 1.0));
+        assert_eq!(orientation(a, b, Vec2::new(0.0, 1.0)), Ordering::Greater);
+        assert_eq!(orientation(a, b, Vec2::new(1.0, 0.0)), Ordering::Less);
+        assert_eq!(orientation(a, b, Vec2::new(4.0, 2.0)), Ordering::Equal);
+        assert_eq!(orientation(a, b, Vec2::new(f64::NAN, 0.0)), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_orientation_near_collinear() {
+        // Points a whisker away from the line through b and c, where rounding
+        // makes the plain float calculation unreliable
+        let (b, c) = (Vec2::new(12.0, 12.0), Vec2::new(24.0, 24.0));
+        let mut naive_mistakes = 0;
+        for i in 0..24 {
+            for j in 0..24 {
+                let a = Vec2::new(nudge(0.5, i), nudge(0.5, j));
+                let exact = exact_orientation(a, b, c);
+                assert_eq!(orientation(a, b, c), exact);
+                // Any order of the same three points agrees
+                assert_eq!(orientation(b, c, a), exact);
+                assert_eq!(orientation(b, a, c), exact.reverse());
+                if naive_orientation(a, b, c) != exact {
+                    naive_mistakes += 1;
+                }
+            }
+        }
+        assert!(naive_mistakes > 0);
+    }
+
+    #[test]
+    fn test_orientation_stages_agree_with_rationals() {
+        // Points rounded onto lines between random points, where the
+        // differences aren't exact and every stage can come into play
+        let mut rng = StdRng::seed_from_u64(43);
+        let mut random_point =
+            || Vec2::new(rng.gen_range(-200.0..200.0), rng.gen_range(-200.0..200.0));
+        for _ in 0..300 {
+            let (a, b, other) = (random_point(), random_point(), random_point());
+            let on_line = a + (b - a) * ((other.x + 200.0) / 400.0);
+            for steps in 0..3 {
+                let c = Vec2::new(nudge(on_line.x, steps), on_line.y);
+                assert_eq!(orientation(a, b, c), exact_orientation(a, b, c));
+            }
+        }
+        // Exactly collinear, but far enough apart in size that none of the
+        // differences are exact, which takes every stage
+        let along = Vec2::new(3.0, 5.0);
+        let (a, c) = (along * 2.0f64.powi(-40), along * 2.0f64.powi(20));
+        assert_eq!(orientation(a, along, c), Ordering::Equal);
+        for steps in 1..3 {
+            let a = Vec2::new(nudge(a.x, steps), a.y);
+            assert_eq!(orientation(a, along, c), exact_orientation(a, along, c));
+            assert_ne!(orientation(a, along, c), Ordering::Equal);
+        }
+        // Too big for the float stages, so left to rationals
+        let huge = Vec2::new(1e200, 3e200);
+        assert_eq!(orientation(Vec2::zero(), huge, huge * 2.0), Ordering::Equal);
+        let off = Vec2::new(nudge(2e200, 1), 6e200);
+        assert_eq!(orientation(Vec2::zero(), huge, off), Ordering::Less);
+    }
+
+    #[test]
+    fn test_lines_intersect_is_consistent() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut random_point = || Vec2::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0));
+        for _ in 0..300 {
+            let (p1, p2, other) = (random_point(), random_point(), random_point());
+            // One end of the second segment rounded onto the first
+            let t = (other.x + 10.0) / 20.0;
+            let q1 = p1 + (p2 - p1) * t;
+            let l1 = Segment::new(p1, p2);
+            let l2 = Segment::new(q1, other);
+            let touching = lines_intersect(l1, l2);
+            assert_eq!(lines_intersect(l2, l1), touching);
+            assert_eq!(lines_intersect(l1.reversed(), l2), touching);
+            assert_eq!(lines_intersect(l1, l2.reversed()), touching);
+            assert_eq!(intersection_time(l1, l2).is_some(), touching);
+            assert_eq!(intersection_time(l2, l1).is_some(), touching);
+        }
+    }
+
+    // A random star-shaped outline around the origin, often concave, given
+    // by the distance to its corners at evenly spaced angles.
+    fn random_star(rng: &mut StdRng) -> Vec<(f64, f64)> {
+        let corners = rng.gen_range(3..16);
+        let start = rng.gen_range(0.0..2.0 * PI);
+        return (0..corners)
+            .map(|i| {
+                let theta = start + 2.0 * PI * i as f64 / corners as f64;
+                (theta, rng.gen_range(1.0..10.0))
+            })
+            .collect();
+    }
+
+    fn corner((theta, r): (f64, f64)) -> Vec2 {
+        return Vec2::new(theta.cos() * r, theta.sin() * r);
+    }
+
+    fn outline(star: &[(f64, f64)], reversed: bool) -> Vec<Segment> {
+        let mut points: Vec<Vec2> = star.iter().map(|&c| corner(c)).collect();
+        if reversed {
+            points.reverse();
+        }
+        return Polygon::new(points).edges().collect();
+    }
+
+    // How far the outline is from the origin in the given direction
+    fn reach(star: &[(f64, f64)], theta: f64) -> f64 {
+        let far = Segment::new(Vec2::zero(), corner((theta, 100.0)));
+        return earliest_intersection(far, outline(star, false).into_iter()).unwrap() * 100.0;
+    }
+
+    #[test]
+    fn test_containment_corners_and_flat_edges() {
+        // A square, with its corners and flat edges on the test rows
+        let square = [
+            Segment::from([0.0, 0.0, 2.0, 0.0]),
+            Segment::from([2.0, 0.0, 2.0, 2.0]),
+            Segment::from([2.0, 2.0, 0.0, 2.0]),
+            Segment::from([0.0, 2.0, 0.0, 0.0]),
+        ];
+        let at = |x: f64, y: f64| containment(Vec2::new(x, y), square.into_iter());
+        assert_eq!(at(1.0, 1.0), Containment::Inside);
+        assert_eq!(at(-1.0, 0.0), Containment::Outside);
+        assert_eq!(at(-1.0, 2.0), Containment::Outside);
+        assert_eq!(at(3.0, 1.0), Containment::Outside);
+        assert_eq!(at(0.0, 0.0), Containment::Boundary);
+        assert_eq!(at(1.0, 2.0), Containment::Boundary);
+        assert_eq!(at(2.0, 1.5), Containment::Boundary);
+        // A diamond's side corners are on the same row as the test point
+        let diamond = [
+            Segment::from([0.0, -1.0, 1.0, 0.0]),
+            Segment::from([1.0, 0.0, 0.0, 1.0]),
+            Segment::from([0.0, 1.0, -1.0, 0.0]),
+            Segment::from([-1.0, 0.0, 0.0, -1.0]),
+        ];
+        let at = |x: f64, y: f64| containment(Vec2::new(x, y), diamond.into_iter());
+        assert_eq!(at(-3.0, 0.0), Containment::Outside);
+        assert_eq!(at(0.5, 0.0), Containment::Inside);
+        assert_eq!(at(1.0, 0.0), Containment::Boundary);
+    }
+
+    #[test]
+    fn test_containment_random_polygons() {
+        let mut rng = StdRng::seed_from_u64(41);
+        for _ in 0..200 {
+            let star = random_star(&mut rng);
+            for reversed in [false, true] {
+                let edges = outline(&star, reversed);
+                let at = |p: Vec2| containment(p, edges.iter().cloned());
+                for &c in star.iter() {
+                    assert_eq!(at(corner(c)), Containment::Boundary);
+                }
+                for _ in 0..20 {
+                    let theta = rng.gen_range(0.0..2.0 * PI);
+                    let r = reach(&star, theta);
+                    let direction = Vec2::new(theta.cos(), theta.sin());
+                    let inside = rng.gen_range(0.0..0.99) * r;
+                    let outside = rng.gen_range(1.01..3.0) * r;
+                    assert_eq!(at(direction * inside), Containment::Inside);
+                    assert_eq!(at(direction * outside), Containment::Outside);
+                    // Points beyond every corner are outside
+                    assert_eq!(at(direction * 11.0), Containment::Outside);
+                }
+            }
+        }
+    }
 }
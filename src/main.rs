@@ -1,30 +1,155 @@
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
 mod actors;
+mod arena;
+mod batch;
+mod bounds;
+mod collision;
 mod config;
+mod export;
+mod font;
+mod geometry;
+#[cfg(test)]
+mod golden;
+mod gravity;
+mod grid;
+mod handling;
 mod intersect;
-mod point;
+mod opengl;
+mod physics;
+mod raster;
+mod raycast;
+mod render;
+mod replay;
 mod scene;
+mod score;
+mod svg;
+mod waves;
+mod world;
 
-use crate::config::Config;
-use crate::scene::{MainScene, Scene};
+use crate::actors::Controls;
+use crate::batch::{aim_at_nearest, WorldBatch, OBSERVATION_SIZE, TICK};
+use crate::config::{Command, Config};
+use crate::export::export;
+use crate::raster::Rasterizer;
+use crate::replay::Replay;
+use crate::scene::{draw_game, MainScene, Scene};
+use crate::svg::SvgRenderer;
+use crate::world::{Outcome, World};
 use opengl_graphics::{GlGraphics, OpenGL};
 use piston_window::WindowSettings;
-use std::cell::RefCell;
-use std::rc::Rc;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::path::Path;
 
 fn main() {
-    let opengl = OpenGL::V3_2;
-    let config = Config::new();
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    match config.command() {
+        Command::Play => play(config),
+        Command::Batch {
+            worlds,
+            ticks,
+            seed,
+        } => batch(config, worlds, ticks, seed),
+        Command::RenderFrame { tick, out, seed } => render_frame(config, tick, &out, seed),
+        Command::Export {
+            replay,
+            out,
+            fps,
+            scale,
+        } => export_replay(config, &replay, &out, fps, scale),
+    }
+}
 
+fn batch(config: Config, worlds: usize, ticks: usize, seed: u64) {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut batch = WorldBatch::new(config, worlds, threads, seed);
+    let mut inputs: Vec<Controls> = batch
+        .observations()
+        .chunks(OBSERVATION_SIZE)
+        .map(aim_at_nearest)
+        .collect();
+    let mut total_reward = 0.0;
+    let mut deaths = 0;
+    for _ in 0..ticks {
+        let step = batch.step_all(&inputs);
+        total_reward += step.rewards.iter().sum::<f32>();
+        deaths += step.done.iter().filter(|&&done| done).count();
+        for (input, observation) in inputs
+            .iter_mut()
+            .zip(step.observations.chunks(OBSERVATION_SIZE))
+        {
+            *input = aim_at_nearest(observation);
+        }
+    }
+    println!(
+        "{} worlds, {} ticks: {} deaths, mean reward {:.2}",
+        worlds,
+        ticks,
+        deaths,
+        total_reward / worlds.max(1) as f32
+    );
+}
+
+// Plays a seeded game with nobody at the controls until the tick, or until
+// the ship is destroyed, and saves the screen at that point, as an SVG if
+// `out` ends in .svg and a PNG otherwise
+fn render_frame(config: Config, tick: usize, out: &str, seed: u64) {
+    let mut world = World::new(1, &config, StdRng::seed_from_u64(seed));
+    for _ in 0..tick {
+        match world.step(TICK, &config) {
+            Outcome::Playing => (),
+            Outcome::ShipDestroyed => break,
+            Outcome::WaveCleared => world.next_wave(&config),
+        }
+    }
+    let out = Path::new(out);
+    let saved = if out.extension().is_some_and(|e| e == "svg") {
+        let mut svg = SvgRenderer::new(config.width(), config.height());
+        draw_game(&world, &config, &mut svg);
+        svg.save(out)
+    } else {
+        let mut raster = Rasterizer::new(config.width() as u32, config.height() as u32);
+        draw_game(&world, &config, &mut raster);
+        raster.save(out)
+    };
+    if let Err(message) = saved {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+}
+
+// Renders a recorded game into a clip
+fn export_replay(config: Config, replay: &str, out: &str, fps: u32, scale: f64) {
+    let exported = Replay::load(Path::new(replay))
+        .and_then(|replay| export(&replay, Path::new(out), fps, scale, &config));
+    match exported {
+        Ok(frames) => println!("{} frames saved to {}", frames, out),
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn play(config: Config) {
+    let opengl = OpenGL::V3_2;
     let dims = [config.width() as u32, config.height() as u32];
     let window_settings = WindowSettings::new("vs-game", dims).exit_on_esc(true);
 
-    let window = Rc::new(RefCell::new(window_settings.build().unwrap()));
+    let mut window = window_settings.build().unwrap();
 
     let mut gl = GlGraphics::new(opengl);
     let mut rng = rand::thread_rng();
 
-    let mut scene: Box<dyn Scene> = Box::new(MainScene::new(1, &config, &mut rng));
-    while let Some(new_scene) = scene.events(&mut rng, window.clone(), &mut gl, &config) {
+    let mut scene: Box<dyn Scene> = Box::new(MainScene::new(1, 1, &config, &mut rng));
+    while let Some(new_scene) = scene.events(&mut rng, &mut window, &mut gl, &config) {
         scene = new_scene;
     }
 }
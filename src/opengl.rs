@@ -0,0 +1,80 @@
+use graphics::line::Line;
+use graphics::math::{multiply, Matrix2d};
+use graphics::polygon::Polygon;
+use graphics::text::Text;
+use graphics::{clear, rectangle, Context, DrawState, Transformed};
+use opengl_graphics::{GlGraphics, GlyphCache};
+
+use crate::geometry::{Segment, Transform, Vec2};
+use crate::render::{Color, Renderer, TransformStack};
+
+// Draws into a window with piston's OpenGL graphics. Text needs a glyph cache,
+// and is skipped without one.
+pub struct GlRenderer<'a, 'b> {
+    gl: &'a mut GlGraphics,
+    ds: DrawState,
+    base: Matrix2d,
+    transforms: TransformStack,
+    glyphs: Option<&'a mut GlyphCache<'b>>,
+}
+
+impl<'a, 'b> GlRenderer<'a, 'b> {
+    pub fn new(c: Context, gl: &'a mut GlGraphics) -> GlRenderer<'a, 'b> {
+        return GlRenderer {
+            gl: gl,
+            ds: c.draw_state,
+            base: c.transform,
+            transforms: TransformStack::new(),
+            glyphs: None,
+        };
+    }
+
+    pub fn with_glyphs(mut self, glyphs: &'a mut GlyphCache<'b>) -> GlRenderer<'a, 'b> {
+        self.glyphs = Some(glyphs);
+        return self;
+    }
+
+    fn transform(&self) -> Matrix2d {
+        return multiply(self.base, self.transforms.current().matrix());
+    }
+}
+
+impl Renderer for GlRenderer<'_, '_> {
+    fn clear(&mut self, color: Color) {
+        clear(color, self.gl);
+    }
+
+    fn line(&mut self, color: Color, width: f64, line: Segment) {
+        let t = self.transform();
+        // Piston's lines are measured from the middle out
+        Line::new(color, width / 2.0).draw(line, &self.ds, t, self.gl);
+    }
+
+    fn polygon(&mut self, color: Color, points: &[Vec2]) {
+        let t = self.transform();
+        let points: Vec<[f64; 2]> = points.iter().map(|p| [p.x, p.y]).collect();
+        Polygon::new(color).draw(&points, &self.ds, t, self.gl);
+    }
+
+    fn rectangle(&mut self, color: Color, corner: Vec2, size: Vec2) {
+        let t = self.transform();
+        rectangle(color, [corner.x, corner.y, size.x, size.y], t, self.gl);
+    }
+
+    fn text(&mut self, color: Color, size: u32, text: &str, position: Vec2) {
+        let t = self.transform().trans(position.x, position.y);
+        if let Some(glyphs) = self.glyphs.as_mut() {
+            Text::new_color(color, size)
+                .draw(text, *glyphs, &self.ds, t, self.gl)
+                .unwrap();
+        }
+    }
+
+    fn push_transform(&mut self, transform: Transform) {
+        self.transforms.push(transform);
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+}
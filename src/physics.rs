@@ -0,0 +1,223 @@
+use crate::actors::Astroid;
+use crate::bounds::Bounds;
+use crate::collision::Contact;
+use crate::geometry::{Polygon, Vec2};
+use crate::grid::{circles_overlap, SpatialGrid};
+
+// Asteroids bounce off each other elastically. Mass is taken from the area of
+// the border polygon, so larger rocks push smaller ones around.
+
+const RESTITUTION: f64 = 1.0;
+// Extra distance added to each push so the pair ends up clear of each other
+// rather than exactly touching
+const SEPARATION_SLOP: f64 = 0.01;
+const MAX_SEPARATION_STEPS: usize = 8;
+
+// Clips the polygon to the half-plane on the left (side = 1.0) or right
+// (side = -1.0) of the line through origin along direction.
+fn clip_to_side(polygon: &Polygon, origin: Vec2, direction: Vec2, side: f64) -> Polygon {
+    let distance = |p: Vec2| side * direction.cross(p - origin);
+    let mut clipped = Vec::with_capacity(polygon.len() + 2);
+    for edge in polygon.edges() {
+        let (d1, d2) = (distance(edge.start), distance(edge.end));
+        if d1 >= 0.0 {
+            clipped.push(edge.start);
+        }
+        if (d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0) {
+            clipped.push(edge.at(d1 / (d1 - d2)));
+        }
+    }
+    return Polygon::new(clipped);
+}
+
+// Cuts a polygon in two along the line through origin along direction. The
+// pieces share the cut edge, so their areas always add up to the original's.
+// A concave polygon cut across more than one lobe keeps the lobes on each
+// side together as one piece joined by zero-width edges along the cut.
+pub fn split_polygon(polygon: &Polygon, origin: Vec2, direction: Vec2) -> (Polygon, Polygon) {
+    return (
+        clip_to_side(polygon, origin, direction, 1.0),
+        clip_to_side(polygon, origin, direction, -1.0),
+    );
+}
+
+// Exchanges momentum along the contact normal. Nothing happens if the
+// asteroids are already moving apart, so a pair that is still overlapping
+// after the last tick doesn't get stuck bouncing in place.
+fn bounce(a: &mut Astroid, b: &mut Astroid, normal: Vec2) {
+    let (ma, mb) = (a.mass(), b.mass());
+    let (va, vb) = (a.obj().velocity(), b.obj().velocity());
+    let approach = (vb - va).dot(normal);
+    if approach >= 0.0 {
+        return;
+    }
+    let impulse = -(1.0 + RESTITUTION) * approach / (1.0 / ma + 1.0 / mb);
+    a.obj_mut().apply_impulse(-normal * impulse);
+    b.obj_mut().apply_impulse(normal * impulse);
+}
+
+// Pushes the pair apart by the depth of each contact until their borders no
+// longer overlap. Each asteroid moves in inverse proportion to its mass, so
+// the centre of mass stays put. Concave asteroids can take more than one push.
+fn separate(a: &mut Astroid, b: &mut Astroid, mut contact: Contact, bounds: &Bounds) {
+    let (ma, mb) = (a.mass(), b.mass());
+    let a_share = mb / (ma + mb);
+    let b_share = ma / (ma + mb);
+    for _ in 0..MAX_SEPARATION_STEPS {
+        let push = contact.normal * (contact.depth + SEPARATION_SLOP);
+        a.obj_mut().translate(push * -a_share, bounds);
+        b.obj_mut().translate(push * b_share, bounds);
+        match a.contact(b, bounds) {
+            Some(next) => contact = next,
+            None => return,
+        }
+    }
+}
+
+// Only pairs that share a cell of the grid and whose bounding circles overlap
+// get their shapes compared. `nearby` is scratch space for grid queries.
+pub fn collide_astroids(
+    astroids: &mut [Astroid],
+    bounds: &Bounds,
+    grid: &mut SpatialGrid,
+    nearby: &mut Vec<usize>,
+) {
+    grid.clear();
+    for (i, astroid) in astroids.iter().enumerate() {
+        grid.insert(i, astroid.obj().position(), astroid.radius());
+    }
+    for i in 0..astroids.len() {
+        let (head, tail) = astroids.split_at_mut(i + 1);
+        let a = &mut head[i];
+        grid.query(a.obj().position(), a.radius(), nearby);
+        for &j in nearby.iter().filter(|&&j| j > i) {
+            let b = &mut tail[j - i - 1];
+            let (pa, pb) = (a.obj().position(), b.obj().position());
+            if !circles_overlap(bounds, pa, a.radius(), pb, b.radius()) {
+                continue;
+            }
+            if let Some(contact) = a.contact(b, bounds) {
+                bounce(a, b, contact.normal);
+                separate(a, b, contact, bounds);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actors::GameObject;
+    use crate::bounds::TEST_BOUNDS;
+    use expectest::prelude::*;
+
+    fn grid() -> SpatialGrid {
+        return SpatialGrid::new(&TEST_BOUNDS, 32.0);
+    }
+
+    fn momentum(astroids: &[Astroid]) -> Vec2 {
+        return astroids
+            .iter()
+            .fold(Vec2::zero(), |p, a| p + a.obj().velocity() * a.mass());
+    }
+
+    fn kinetic_energy(astroids: &[Astroid]) -> f64 {
+        return astroids
+            .iter()
+            .map(|a| {
+                let v = a.obj().velocity();
+                0.5 * a.mass() * v.dot(v)
+            })
+            .sum();
+    }
+
+    fn expect_momentum_close(before: Vec2, after: Vec2) {
+        expect!(after.x).to(be_close_to(before.x).delta(1e-6));
+        expect!(after.y).to(be_close_to(before.y).delta(1e-6));
+    }
+
+    #[test]
+    fn test_split_polygon() {
+        let square = Polygon::square(5.0);
+        let (left, right) = split_polygon(&square, Vec2::new(2.0, 0.0), Vec2::new(0.0, 1.0));
+        expect!(left.area()).to(be_close_to(70.0));
+        expect!(right.area()).to(be_close_to(30.0));
+        // A diagonal cut through a vertex
+        let (left, right) = split_polygon(&square, Vec2::zero(), Vec2::new(1.0, 1.0));
+        expect!(left.area()).to(be_close_to(50.0));
+        expect!(right.area()).to(be_close_to(50.0));
+        // A line that misses leaves one side empty
+        let (left, right) = split_polygon(&square, Vec2::new(9.0, 0.0), Vec2::new(0.0, 1.0));
+        expect!(left.area()).to(be_close_to(100.0));
+        expect!(right.len()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_equal_masses_swap_velocities() {
+        let mut astroids = vec![
+            Astroid::with_border(
+                GameObject::new(Vec2::new(96.0, 100.0), Vec2::new(50.0, 0.0)),
+                2,
+                Polygon::square(5.0),
+            ),
+            Astroid::with_border(
+                GameObject::new(Vec2::new(104.0, 100.0), Vec2::zero()),
+                2,
+                Polygon::square(5.0),
+            ),
+        ];
+        let before = momentum(&astroids);
+        collide_astroids(&mut astroids, &TEST_BOUNDS, &mut grid(), &mut Vec::new());
+        expect_momentum_close(before, momentum(&astroids));
+        expect!(astroids[0].obj().velocity().x).to(be_close_to(0.0).delta(1e-6));
+        expect!(astroids[1].obj().velocity().x).to(be_close_to(50.0).delta(1e-6));
+        assert!(!astroids[0].collides_with(&astroids[1], &TEST_BOUNDS));
+    }
+
+    #[test]
+    fn test_separating_astroids_are_not_bounced() {
+        let mut astroids = vec![
+            Astroid::with_border(
+                GameObject::new(Vec2::new(96.0, 100.0), Vec2::new(-50.0, 0.0)),
+                2,
+                Polygon::square(5.0),
+            ),
+            Astroid::with_border(
+                GameObject::new(Vec2::new(104.0, 100.0), Vec2::zero()),
+                2,
+                Polygon::square(5.0),
+            ),
+        ];
+        collide_astroids(&mut astroids, &TEST_BOUNDS, &mut grid(), &mut Vec::new());
+        expect!(astroids[0].obj().velocity().x).to(be_close_to(-50.0).delta(1e-6));
+    }
+
+    #[test]
+    fn test_simulation_conserves_momentum() {
+        let mut astroids = vec![
+            Astroid::with_border(
+                GameObject::new(Vec2::new(40.0, 100.0), Vec2::new(60.0, 0.0)),
+                3,
+                Polygon::square(8.0),
+            ),
+            Astroid::with_border(
+                GameObject::new(Vec2::new(160.0, 104.0), Vec2::new(-40.0, 0.0)),
+                2,
+                Polygon::square(4.0),
+            ),
+        ];
+        let momentum_before = momentum(&astroids);
+        let energy_before = kinetic_energy(&astroids);
+        for _ in 0..240 {
+            for astroid in astroids.iter_mut() {
+                astroid.go(1.0 / 120.0, &TEST_BOUNDS);
+            }
+            collide_astroids(&mut astroids, &TEST_BOUNDS, &mut grid(), &mut Vec::new());
+            assert!(!astroids[0].collides_with(&astroids[1], &TEST_BOUNDS));
+        }
+        expect_momentum_close(momentum_before, momentum(&astroids));
+        expect!(kinetic_energy(&astroids)).to(be_close_to(energy_before).delta(1e-6));
+        // The light asteroid was knocked back the way it came.
+        assert!(astroids[1].obj().velocity().x > 0.0);
+    }
+}
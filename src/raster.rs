@@ -0,0 +1,281 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+
+use crate::font::{filled, glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::geometry::{Segment, Transform, Vec2};
+use crate::intersect::point_segment_distance;
+use crate::render::{Color, Renderer, TransformStack};
+
+// Draws into an image in memory, so frames can be saved without a window or a
+// graphics card. Edges are anti-aliased: lines by how far each pixel's centre
+// is from them, and filled shapes by how much of each pixel they cover.
+
+// Rows each pixel is split into when working out how much of it a shape fills
+const SUBSAMPLES: usize = 4;
+
+pub struct Rasterizer {
+    image: RgbaImage,
+    transforms: TransformStack,
+    // How much of each pixel in a row is covered, kept between fills
+    coverage: Vec<f32>,
+}
+
+impl Rasterizer {
+    pub fn new(width: u32, height: u32) -> Rasterizer {
+        return Rasterizer {
+            image: RgbaImage::new(width, height),
+            transforms: TransformStack::new(),
+            coverage: vec![0.0; width as usize],
+        };
+    }
+
+    #[cfg(test)]
+    pub fn image(&self) -> &RgbaImage {
+        return &self.image;
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        return self.image;
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return self
+            .image
+            .save(path)
+            .map_err(|e| format!("couldn't save {}: {}", path.display(), e));
+    }
+
+    // Mixes the colour into the pixel, as if it covered that much of it
+    fn blend(&mut self, x: u32, y: u32, color: Color, coverage: f32) {
+        let alpha = color[3] * coverage.min(1.0);
+        if alpha <= 0.0 {
+            return;
+        }
+        let Rgba(pixel) = self.image.get_pixel_mut(x, y);
+        for i in 0..3 {
+            let mixed = color[i] * alpha + pixel[i] as f32 / 255.0 * (1.0 - alpha);
+            pixel[i] = (mixed * 255.0).round() as u8;
+        }
+        let covered = alpha + pixel[3] as f32 / 255.0 * (1.0 - alpha);
+        pixel[3] = (covered * 255.0).round() as u8;
+    }
+
+    // Pixels the range of coordinates touches, clipped to the image
+    fn pixels(from: f64, to: f64, size: u32) -> std::ops::Range<u32> {
+        let start = from.floor().clamp(0.0, size as f64) as u32;
+        let end = to.ceil().clamp(0.0, size as f64) as u32;
+        return start..end;
+    }
+
+    // Fills the inside of an outline already in pixels, counting a point as
+    // inside if the outline winds around it
+    fn fill(&mut self, color: Color, points: &[Vec2]) {
+        if points.len() < 3 {
+            return;
+        }
+        let (width, height) = self.image.dimensions();
+        let top = points.iter().fold(f64::INFINITY, |y, p| y.min(p.y));
+        let bottom = points.iter().fold(f64::NEG_INFINITY, |y, p| y.max(p.y));
+        let edges = || (0..points.len()).map(|i| (points[i], points[(i + 1) % points.len()]));
+        let mut crossings: Vec<(f64, i32)> = Vec::new();
+        for row in Rasterizer::pixels(top, bottom, height) {
+            let mut coverage = std::mem::take(&mut self.coverage);
+            coverage.iter_mut().for_each(|c| *c = 0.0);
+            let (mut left, mut right) = (width, 0);
+            for sample in 0..SUBSAMPLES {
+                let y = row as f64 + (sample as f64 + 0.5) / SUBSAMPLES as f64;
+                crossings.clear();
+                for (a, b) in edges() {
+                    if (a.y <= y) != (b.y <= y) {
+                        let x = a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x);
+                        crossings.push((x, if b.y > a.y { 1 } else { -1 }));
+                    }
+                }
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    if winding == 0 {
+                        continue;
+                    }
+                    let (start, end) = (pair[0].0, pair[1].0);
+                    for x in Rasterizer::pixels(start, end, width) {
+                        let inside = end.min(x as f64 + 1.0) - start.max(x as f64);
+                        coverage[x as usize] += inside as f32 / SUBSAMPLES as f32;
+                        left = left.min(x);
+                        right = right.max(x + 1);
+                    }
+                }
+            }
+            for x in left..right {
+                self.blend(x, row, color, coverage[x as usize]);
+            }
+            self.coverage = coverage;
+        }
+    }
+}
+
+impl Renderer for Rasterizer {
+    fn clear(&mut self, color: Color) {
+        let pixel = color.map(|c| (c * 255.0).round() as u8);
+        for p in self.image.pixels_mut() {
+            *p = Rgba(pixel);
+        }
+    }
+
+    fn line(&mut self, color: Color, width: f64, line: Segment) {
+        let transform = self.transforms.current();
+        let line = Segment::new(transform.apply(line.start), transform.apply(line.end));
+        let width = width * transform.stretch();
+        // Lines thinner than a pixel are drawn a pixel wide, but fainter
+        let (half, faint) = (width.max(1.0) / 2.0, width.min(1.0) as f32);
+        let (columns, rows) = self.image.dimensions();
+        let reach = half + 0.5;
+        let xs = Rasterizer::pixels(
+            line.start.x.min(line.end.x) - reach,
+            line.start.x.max(line.end.x) + reach,
+            columns,
+        );
+        let ys = Rasterizer::pixels(
+            line.start.y.min(line.end.y) - reach,
+            line.start.y.max(line.end.y) + reach,
+            rows,
+        );
+        for y in ys {
+            for x in xs.clone() {
+                let centre = Vec2::new(x as f64 + 0.5, y as f64 + 0.5);
+                let distance = point_segment_distance(centre, line);
+                let coverage = (reach - distance).clamp(0.0, 1.0) as f32;
+                self.blend(x, y, color, coverage * faint);
+            }
+        }
+    }
+
+    fn polygon(&mut self, color: Color, points: &[Vec2]) {
+        let transform = self.transforms.current();
+        let points: Vec<Vec2> = points.iter().map(|&p| transform.apply(p)).collect();
+        self.fill(color, &points);
+    }
+
+    fn rectangle(&mut self, color: Color, corner: Vec2, size: Vec2) {
+        let corners = [
+            corner,
+            corner + Vec2::new(size.x, 0.0),
+            corner + size,
+            corner + Vec2::new(0.0, size.y),
+        ];
+        self.polygon(color, &corners);
+    }
+
+    // Text is drawn with the built in font, filling each of its cells
+    fn text(&mut self, color: Color, size: u32, text: &str, position: Vec2) {
+        let cell = size as f64 / (GLYPH_HEIGHT + 1) as f64;
+        let advance = Vec2::new(cell * (GLYPH_WIDTH + 1) as f64, 0.0);
+        let top = position - Vec2::new(0.0, cell * GLYPH_HEIGHT as f64);
+        for (i, c) in text.chars().enumerate() {
+            let glyph = glyph(c);
+            let origin = top + advance * i as f64;
+            for row in 0..GLYPH_HEIGHT {
+                for column in 0..GLYPH_WIDTH {
+                    if filled(&glyph, row, column) {
+                        let offset = Vec2::new(column as f64, row as f64) * cell;
+                        self.rectangle(color, origin + offset, Vec2::new(cell, cell));
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_transform(&mut self, transform: Transform) {
+        self.transforms.push(transform);
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::{BLACK, WHITE};
+    use expectest::prelude::*;
+
+    fn brightness(raster: &Rasterizer, x: u32, y: u32) -> u8 {
+        return raster.image().get_pixel(x, y)[0];
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut raster = Rasterizer::new(4, 3);
+        raster.clear(WHITE);
+        assert!(raster.image().pixels().all(|p| *p == Rgba([255; 4])));
+    }
+
+    #[test]
+    fn test_line() {
+        let mut raster = Rasterizer::new(20, 20);
+        raster.clear(BLACK);
+        raster.line(WHITE, 1.0, Segment::from([2.0, 10.5, 18.0, 10.5]));
+        expect!(brightness(&raster, 10, 10)).to(be_equal_to(255));
+        expect!(brightness(&raster, 10, 9)).to(be_equal_to(0));
+        expect!(brightness(&raster, 10, 11)).to(be_equal_to(0));
+        // Edges fade out rather than stepping
+        raster.clear(BLACK);
+        raster.line(WHITE, 1.0, Segment::from([2.0, 10.0, 18.0, 10.0]));
+        expect!(brightness(&raster, 10, 9)).to(be_equal_to(128));
+        expect!(brightness(&raster, 10, 10)).to(be_equal_to(128));
+        // Thin lines are fainter
+        raster.clear(BLACK);
+        raster.line(WHITE, 0.5, Segment::from([2.0, 10.5, 18.0, 10.5]));
+        expect!(brightness(&raster, 10, 10)).to(be_equal_to(128));
+    }
+
+    #[test]
+    fn test_polygon() {
+        let mut raster = Rasterizer::new(20, 20);
+        raster.clear(BLACK);
+        let mut square = [
+            Vec2::new(4.0, 4.0),
+            Vec2::new(12.5, 4.0),
+            Vec2::new(12.5, 12.0),
+            Vec2::new(4.0, 12.0),
+        ];
+        raster.polygon(WHITE, &square);
+        expect!(brightness(&raster, 4, 4)).to(be_equal_to(255));
+        expect!(brightness(&raster, 11, 11)).to(be_equal_to(255));
+        expect!(brightness(&raster, 12, 8)).to(be_equal_to(128));
+        expect!(brightness(&raster, 3, 8)).to(be_equal_to(0));
+        expect!(brightness(&raster, 8, 12)).to(be_equal_to(0));
+        // The same either way round
+        let mut reversed = Rasterizer::new(20, 20);
+        reversed.clear(BLACK);
+        square.reverse();
+        reversed.polygon(WHITE, &square);
+        assert!(reversed.image() == raster.image());
+    }
+
+    #[test]
+    fn test_transforms() {
+        let mut raster = Rasterizer::new(20, 20);
+        raster.clear(BLACK);
+        raster.push_transform(Transform::translation(Vec2::new(10.0, 5.0)));
+        raster.rectangle(WHITE, Vec2::zero(), Vec2::new(2.0, 2.0));
+        raster.pop_transform();
+        expect!(brightness(&raster, 11, 6)).to(be_equal_to(255));
+        expect!(brightness(&raster, 1, 1)).to(be_equal_to(0));
+        expect!(brightness(&raster, 12, 6)).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_text() {
+        let mut raster = Rasterizer::new(40, 20);
+        raster.clear(BLACK);
+        raster.text(WHITE, 8, "L", Vec2::new(2.0, 10.0));
+        // The L's upright, from the top of the glyph down to the baseline
+        expect!(brightness(&raster, 2, 3)).to(be_equal_to(255));
+        expect!(brightness(&raster, 2, 9)).to(be_equal_to(255));
+        expect!(brightness(&raster, 3, 3)).to(be_equal_to(0));
+        expect!(brightness(&raster, 2, 10)).to(be_equal_to(0));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::actors::OwnerId;
+use crate::bounds::{Boundary, Bounds};
+use crate::geometry::{Segment, Vec2};
+use crate::intersect::{intersection_time, point_segment_distance};
+use std::ops::BitOr;
+
+// Straight-line queries for hitscan weapons, line of sight and bots that
+// sense their surroundings. A ray that runs off the edge of a wrapping screen
+// carries on from the opposite edge.
+
+// Which kinds of things a ray can hit
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Mask(u8);
+
+impl Mask {
+    pub const ASTROIDS: Mask = Mask(1);
+    pub const SHIPS: Mask = Mask(2);
+    pub const WALLS: Mask = Mask(4);
+    #[cfg(test)]
+    pub const ALL: Mask = Mask(7);
+
+    pub fn contains(self, other: Mask) -> bool {
+        return self.0 & other.0 == other.0;
+    }
+}
+
+impl BitOr<Mask> for Mask {
+    type Output = Mask;
+
+    fn bitor(self, other: Mask) -> Self::Output {
+        return Mask(self.0 | other.0);
+    }
+}
+
+// What a ray hit: an asteroid by its index in the world, a ship by who flies
+// it, or a wall by its index in the arena
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Entity {
+    Astroid(usize),
+    Ship(OwnerId),
+    Wall(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hit {
+    // How far the ray went, counting any trips around the screen
+    pub distance: f64,
+    // Where it hit, on the screen
+    pub point: Vec2,
+    // Unit vector out of the surface, back toward where the ray came from
+    pub normal: Vec2,
+    pub entity: Entity,
+}
+
+// How many times across the screen a wrapping ray goes before it gives up,
+// which keeps a very long ray from breaking into endless pieces
+const MAX_LAPS: f64 = 4.0;
+
+// Buffers for casting rays, kept between casts so that casting doesn't need
+// any new memory
+#[derive(Clone, Default)]
+pub struct Scratch {
+    pub pieces: Vec<(f64, Segment)>,
+    pub edges: Vec<(Entity, Segment)>,
+}
+
+// Breaks the ray into pieces that each stay on the screen, along with how far
+// along the ray each piece starts, replacing what's in `pieces`. Without
+// wrapping it's just the one piece. Wrapping rays stop after MAX_LAPS laps.
+pub fn pieces(
+    bounds: &Bounds,
+    origin: Vec2,
+    direction: Vec2,
+    max_dist: f64,
+    pieces: &mut Vec<(f64, Segment)>,
+) {
+    pieces.clear();
+    if bounds.boundary != Boundary::Wrap {
+        pieces.push((0.0, Segment::new(origin, origin + direction * max_dist)));
+        return;
+    }
+    // Distance along the ray to the edge it's heading for
+    let to_edge = |p: f64, d: f64, max: f64| {
+        if d > 0.0 {
+            return (max - p) / d;
+        } else if d < 0.0 {
+            return p / -d;
+        }
+        return f64::INFINITY;
+    };
+    let max_dist = max_dist.min(MAX_LAPS * (bounds.width + bounds.height));
+    let mut start = bounds.translate(origin, Vec2::zero());
+    let mut travelled = 0.0;
+    while travelled < max_dist {
+        // A point on the edge it's heading away from starts from the far edge
+        if start.x <= 0.0 && direction.x < 0.0 {
+            start.x = bounds.width;
+        }
+        if start.y <= 0.0 && direction.y < 0.0 {
+            start.y = bounds.height;
+        }
+        let step = to_edge(start.x, direction.x, bounds.width)
+            .min(to_edge(start.y, direction.y, bounds.height))
+            .min(max_dist - travelled);
+        pieces.push((travelled, Segment::new(start, start + direction * step)));
+        travelled += step;
+        start = bounds.translate(start, direction * step);
+    }
+}
+
+// Whether any of the pieces comes within `radius` of `centre`, so that
+// things the ray can't reach are skipped before looking at their edges
+pub fn passes_near(pieces: &[(f64, Segment)], centre: Vec2, radius: f64) -> bool {
+    return pieces
+        .iter()
+        .any(|&(_, piece)| point_segment_distance(centre, piece) <= radius);
+}
+
+// The first of `edges`, given in screen coordinates along with what they
+// belong to, that the ray hits along its pieces. A ray starting inside
+// something hits it on the way out.
+pub fn first_hit(
+    pieces: &[(f64, Segment)],
+    direction: Vec2,
+    edges: &[(Entity, Segment)],
+) -> Option<Hit> {
+    for &(start, piece) in pieces {
+        let piece_length = piece.length();
+        let nearest = edges
+            .iter()
+            .filter_map(|&(entity, edge)| Some((intersection_time(piece, edge)?, entity, edge)))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((t, entity, edge)) = nearest else {
+            continue;
+        };
+        // A zero-length edge can only be hit head on
+        let mut normal = edge.normal().unwrap_or(-direction);
+        if normal.dot(direction) > 0.0 {
+            normal = -normal;
+        }
+        return Some(Hit {
+            distance: start + t * piece_length,
+            point: piece.at(t),
+            normal: normal,
+            entity: entity,
+        });
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounds::TEST_BOUNDS;
+    use expectest::prelude::*;
+
+    fn ray(bounds: &Bounds, origin: Vec2, direction: Vec2, max_dist: f64) -> Vec<(f64, Segment)> {
+        let mut ray = Vec::new();
+        pieces(bounds, origin, direction, max_dist, &mut ray);
+        return ray;
+    }
+
+    // The first of `edges` that the ray hits within `max_dist`
+    fn cast(
+        bounds: &Bounds,
+        origin: Vec2,
+        direction: Vec2,
+        max_dist: f64,
+        edges: &[(Entity, Segment)],
+    ) -> Option<Hit> {
+        let direction = direction.normalize()?;
+        return first_hit(&ray(bounds, origin, direction, max_dist), direction, edges);
+    }
+
+    fn wall() -> Segment {
+        return Segment::from([150.0, 50.0, 150.0, 150.0]);
+    }
+
+    #[test]
+    fn test_mask() {
+        let mask = Mask::ASTROIDS | Mask::WALLS;
+        assert!(mask.contains(Mask::ASTROIDS));
+        assert!(!mask.contains(Mask::SHIPS));
+        assert!(Mask::ALL.contains(mask));
+    }
+
+    #[test]
+    fn test_pieces() {
+        let right = Vec2::new(1.0, 0.0);
+        expect!(ray(&TEST_BOUNDS, Vec2::new(100.0, 100.0), right, 50.0)).to(be_equal_to(vec![(
+            0.0,
+            Segment::from([100.0, 100.0, 150.0, 100.0]),
+        )]));
+        // Off the right edge and back in from the left
+        expect!(ray(&TEST_BOUNDS, Vec2::new(150.0, 100.0), right, 100.0)).to(be_equal_to(vec![
+            (0.0, Segment::from([150.0, 100.0, 200.0, 100.0])),
+            (50.0, Segment::from([0.0, 100.0, 50.0, 100.0])),
+        ]));
+        // Starting on the edge it's heading away from
+        let left = Vec2::new(-1.0, 0.0);
+        expect!(ray(&TEST_BOUNDS, Vec2::new(0.0, 100.0), left, 50.0)).to(be_equal_to(vec![(
+            0.0,
+            Segment::from([200.0, 100.0, 150.0, 100.0]),
+        )]));
+        // More than once around
+        expect!(ray(&TEST_BOUNDS, Vec2::new(100.0, 100.0), right, 500.0).len()).to(be_equal_to(3));
+        // Only so many times around
+        let diagonal = Vec2::new(3.0, 4.0) * 0.2;
+        let far = ray(&TEST_BOUNDS, Vec2::new(100.0, 100.0), diagonal, 1e12);
+        assert!(far.len() < 40);
+        let (start, last) = far[far.len() - 1];
+        expect!(start + last.length()).to(be_close_to(1600.0));
+        let forever = ray(
+            &TEST_BOUNDS,
+            Vec2::new(100.0, 100.0),
+            diagonal,
+            f64::INFINITY,
+        );
+        expect!(forever).to(be_equal_to(far));
+        // No wrapping, no pieces
+        let open = Bounds::new(200.0, 200.0, Boundary::Open);
+        expect!(ray(&open, Vec2::new(150.0, 100.0), right, 100.0)).to(be_equal_to(vec![(
+            0.0,
+            Segment::from([150.0, 100.0, 250.0, 100.0]),
+        )]));
+        // Zero length, no pieces
+        expect!(ray(&TEST_BOUNDS, Vec2::new(100.0, 100.0), right, 0.0).len()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_passes_near() {
+        let ray = ray(
+            &TEST_BOUNDS,
+            Vec2::new(150.0, 100.0),
+            Vec2::new(1.0, 0.0),
+            100.0,
+        );
+        assert!(passes_near(&ray, Vec2::new(180.0, 105.0), 5.0));
+        assert!(passes_near(&ray, Vec2::new(20.0, 95.0), 5.0));
+        assert!(!passes_near(&ray, Vec2::new(100.0, 100.0), 5.0));
+        assert!(!passes_near(&ray, Vec2::new(180.0, 110.0), 5.0));
+    }
+
+    #[test]
+    fn test_cast() {
+        let edges = [(Entity::Wall(0), wall())];
+        let hit = cast(
+            &TEST_BOUNDS,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(2.0, 0.0),
+            80.0,
+            &edges,
+        );
+        expect!(hit).to(be_equal_to(Some(Hit {
+            distance: 50.0,
+            point: Vec2::new(150.0, 100.0),
+            normal: Vec2::new(-1.0, 0.0),
+            entity: Entity::Wall(0),
+        })));
+        // Too short, or facing away
+        expect!(cast(
+            &TEST_BOUNDS,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(1.0, 0.0),
+            40.0,
+            &edges
+        ))
+        .to(be_none());
+        expect!(cast(
+            &TEST_BOUNDS,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(-1.0, 0.0),
+            40.0,
+            &edges
+        ))
+        .to(be_none());
+        expect!(cast(
+            &TEST_BOUNDS,
+            Vec2::new(100.0, 100.0),
+            Vec2::zero(),
+            80.0,
+            &edges
+        ))
+        .to(be_none());
+    }
+
+    #[test]
+    fn test_cast_wraps() {
+        let edges = [
+            (Entity::Wall(0), wall()),
+            (Entity::Astroid(3), Segment::from([20.0, 90.0, 20.0, 110.0])),
+        ];
+        // Leftward around the edge of the screen to the wall
+        let hit = cast(
+            &TEST_BOUNDS,
+            Vec2::new(10.0, 100.0),
+            Vec2::new(-1.0, 0.0),
+            100.0,
+            &edges,
+        );
+        let hit = hit.unwrap();
+        expect!(hit.distance).to(be_close_to(60.0));
+        expect!(hit.point).to(be_equal_to(Vec2::new(150.0, 100.0)));
+        expect!(hit.normal).to(be_equal_to(Vec2::new(1.0, 0.0)));
+        // Rightward, the nearer asteroid is in the way
+        let hit = cast(
+            &TEST_BOUNDS,
+            Vec2::new(10.0, 100.0),
+            Vec2::new(1.0, 0.0),
+            100.0,
+            &edges,
+        );
+        expect!(hit.unwrap().entity).to(be_equal_to(Entity::Astroid(3)));
+        // All the way around to hit the asteroid from behind
+        let hit = cast(
+            &TEST_BOUNDS,
+            Vec2::new(30.0, 100.0),
+            Vec2::new(1.0, 0.0),
+            200.0,
+            &edges[1..],
+        );
+        expect!(hit.unwrap().distance).to(be_close_to(190.0));
+    }
+}
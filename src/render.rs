@@ -0,0 +1,91 @@
+use crate::geometry::{Segment, Transform, Vec2};
+
+// Everything the game draws goes through a Renderer, so the same drawing code
+// can put a frame in a window or anywhere else. Coordinates are in the
+// game's units, moved by whatever transforms have been pushed.
+
+// Red, green, blue and alpha, from 0 to 1
+pub type Color = [f32; 4];
+
+pub const WHITE: Color = [1.0, 1.0, 1.0, 1.0];
+pub const BLACK: Color = [0.0, 0.0, 0.0, 1.0];
+
+pub trait Renderer {
+    // Fills the whole frame, ignoring any transforms
+    fn clear(&mut self, color: Color);
+
+    fn line(&mut self, color: Color, width: f64, line: Segment);
+
+    // Fills the inside of the outline
+    fn polygon(&mut self, color: Color, points: &[Vec2]);
+
+    // Fills the rectangle with its top left corner at `corner`
+    fn rectangle(&mut self, color: Color, corner: Vec2, size: Vec2);
+
+    // Writes text with its baseline starting at `position`, `size` pixels tall
+    fn text(&mut self, color: Color, size: u32, text: &str, position: Vec2);
+
+    // Everything drawn until the matching pop is moved by the transform
+    // first, and then by any pushed before it
+    fn push_transform(&mut self, transform: Transform);
+
+    fn pop_transform(&mut self);
+}
+
+// Transforms pushed onto a renderer, kept combined so backends can apply
+// them with one step. Popping more than was pushed leaves the identity.
+#[derive(Clone, Debug)]
+pub struct TransformStack {
+    stack: Vec<Transform>,
+}
+
+impl TransformStack {
+    pub fn new() -> TransformStack {
+        return TransformStack {
+            stack: vec![Transform::identity()],
+        };
+    }
+
+    pub fn push(&mut self, transform: Transform) {
+        let combined = transform.then(&self.current());
+        self.stack.push(combined);
+    }
+
+    pub fn pop(&mut self) {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+        }
+    }
+
+    pub fn current(&self) -> Transform {
+        return self.stack[self.stack.len() - 1];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_transform_stack() {
+        let mut stack = TransformStack::new();
+        let p = Vec2::new(1.0, 2.0);
+        expect!(stack.current().apply(p)).to(be_equal_to(p));
+        stack.push(Transform::translation(Vec2::new(10.0, 0.0)));
+        stack.push(Transform::translation(Vec2::new(0.0, 5.0)));
+        expect!(stack.current().apply(p)).to(be_equal_to(Vec2::new(11.0, 7.0)));
+        stack.pop();
+        expect!(stack.current().apply(p)).to(be_equal_to(Vec2::new(11.0, 2.0)));
+        // The latest transform applies first
+        stack.push(Transform::rotation(PI / 2.0));
+        let turned = stack.current().apply(Vec2::new(1.0, 0.0));
+        expect!(turned.x).to(be_close_to(10.0));
+        expect!(turned.y).to(be_close_to(1.0));
+        stack.pop();
+        stack.pop();
+        stack.pop();
+        expect!(stack.current().apply(p)).to(be_equal_to(p));
+    }
+}
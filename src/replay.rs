@@ -0,0 +1,183 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::path::Path;
+
+use crate::actors::Controls;
+use crate::config::Config;
+use crate::world::World;
+
+// Everything needed to play a game over again: how it started, and the
+// controls on every tick, which is enough because a world does the same thing
+// every time it's given the same seed and controls. Games are stepped a fixed
+// `TICK` at a time, however fast the frames come, so playing the ticks back
+// at that rate is the same game. The options the game was played with, like
+// the arena, aren't part of it, and need giving again.
+//
+// Replays are saved as text, one setting to a line, with each run of ticks
+// where the controls didn't change as
+// `controls <ticks> <thrust> <turn> <fire, 0 or 1>`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    difficulty: usize,
+    seed: u64,
+    controls: Vec<(usize, Controls)>,
+}
+
+impl Replay {
+    pub fn new(difficulty: usize, seed: u64) -> Replay {
+        return Replay {
+            difficulty: difficulty,
+            seed: seed,
+            controls: Vec::new(),
+        };
+    }
+
+    // The world as it was when the game started
+    pub fn start(&self, config: &Config) -> World {
+        return World::new(self.difficulty, config, StdRng::seed_from_u64(self.seed));
+    }
+
+    // Adds one more tick
+    pub fn record(&mut self, controls: Controls) {
+        match self.controls.last_mut() {
+            Some((ticks, last)) if *last == controls => *ticks += 1,
+            _ => self.controls.push((1, controls)),
+        }
+    }
+
+    // The controls on each tick in turn
+    pub fn ticks(&self) -> impl Iterator<Item = Controls> + '_ {
+        return self
+            .controls
+            .iter()
+            .flat_map(|&(ticks, controls)| std::iter::repeat_n(controls, ticks));
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        return Replay::parse(&text).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.to_text())
+            .map_err(|e| format!("couldn't save {}: {}", path.display(), e));
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut replay = Replay::new(1, 0);
+        for (i, line) in text.lines().enumerate() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let not_understood = || format!("line {}: can't understand {}", i + 1, line);
+            match words.as_slice() {
+                [] => continue,
+                [first, ..] if first.starts_with('#') => continue,
+                ["difficulty", difficulty] => {
+                    replay.difficulty = difficulty.parse().map_err(|_| not_understood())?
+                }
+                ["seed", seed] => replay.seed = seed.parse().map_err(|_| not_understood())?,
+                ["controls", ticks, thrust, turn, fire] => {
+                    let ticks = ticks.parse().map_err(|_| not_understood())?;
+                    let controls = Controls {
+                        thrust: thrust.parse().map_err(|_| not_understood())?,
+                        turn: turn.parse().map_err(|_| not_understood())?,
+                        fire: match *fire {
+                            "0" => false,
+                            "1" => true,
+                            _ => return Err(not_understood()),
+                        },
+                    };
+                    replay.controls.push((ticks, controls));
+                }
+                _ => return Err(not_understood()),
+            }
+        }
+        return Ok(replay);
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("difficulty {}\nseed {}\n", self.difficulty, self.seed);
+        for (ticks, controls) in self.controls.iter() {
+            text += &format!(
+                "controls {} {} {} {}\n",
+                ticks, controls.thrust, controls.turn, controls.fire as u8
+            );
+        }
+        return text;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::batch::TICK;
+    use crate::world::Outcome;
+    use expectest::prelude::*;
+
+    fn turning(turn: f64) -> Controls {
+        return Controls {
+            thrust: 0.5,
+            turn: turn,
+            fire: true,
+        };
+    }
+
+    #[test]
+    fn test_record() {
+        let mut replay = Replay::new(1, 7);
+        replay.record(Controls::default());
+        replay.record(turning(-1.0));
+        replay.record(turning(-1.0));
+        replay.record(Controls::default());
+        expect!(replay.controls.len()).to(be_equal_to(3));
+        let ticks: Vec<Controls> = replay.ticks().collect();
+        expect!(ticks).to(be_equal_to(vec![
+            Controls::default(),
+            turning(-1.0),
+            turning(-1.0),
+            Controls::default(),
+        ]));
+    }
+
+    #[test]
+    fn test_text() {
+        let mut replay = Replay::new(2, 7);
+        replay.record(turning(0.25));
+        replay.record(Controls::default());
+        replay.record(Controls::default());
+        expect!(replay.to_text()).to(be_equal_to(
+            "difficulty 2\nseed 7\ncontrols 1 0.5 0.25 1\ncontrols 2 0 0 0\n".to_string(),
+        ));
+        expect!(Replay::parse(&replay.to_text())).to(be_ok().value(replay));
+        let commented = Replay::parse("# A short one\n\nseed 3\ncontrols 60 1 0 0\n").unwrap();
+        expect!(commented.ticks().count()).to(be_equal_to(60));
+        assert!(Replay::parse("seed -3").is_err());
+        assert!(Replay::parse("controls 60 1 0 yes").is_err());
+        assert!(Replay::parse("controls 60 1 0").is_err());
+        assert!(Replay::parse("speed 3").is_err());
+    }
+
+    #[test]
+    fn test_plays_back_the_same() {
+        let config = Config::new();
+        let mut played = Replay::new(2, 11);
+        let mut world = played.start(&config);
+        for i in 0..600 {
+            let controls = turning(if i % 200 < 100 { 1.0 } else { -0.5 });
+            world.steer(controls);
+            played.record(world.spaceship().controls());
+            if world.step(TICK, &config) != Outcome::Playing {
+                break;
+            }
+        }
+        let mut replayed = played.start(&config);
+        for controls in played.ticks() {
+            replayed.steer(controls);
+            replayed.step(TICK, &config);
+        }
+        expect!(replayed.spaceship().position()).to(be_equal_to(world.spaceship().position()));
+        expect!(replayed.hits()).to(be_equal_to(world.hits()));
+        expect!(replayed.astroids().len()).to(be_equal_to(world.astroids().len()));
+    }
+}
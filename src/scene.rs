@@ -1,24 +1,44 @@
-use graphics::text::Text;
-use graphics::{DrawState, Transformed};
 use opengl_graphics::GlyphCache;
 use opengl_graphics::{GlGraphics, TextureSettings};
-use piston::input::{Button, Event, Input, Key, RenderArgs, UpdateArgs};
+use piston::input::{Button, Event, Input, Key, UpdateArgs};
 use piston::{ButtonArgs, ButtonState, Loop};
 use piston_window::PistonWindow as Window;
-use rand::RngCore;
-use std::borrow::BorrowMut;
-use std::cell::RefCell;
-use std::iter::repeat;
-use std::rc::Rc;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 
-use crate::actors::{Astroid, Bullet, Spaceship};
+use crate::batch::TICK;
 use crate::config::Config;
+use crate::geometry::Vec2;
+use crate::opengl::GlRenderer;
+use crate::render::{Renderer, BLACK, WHITE};
+use crate::replay::Replay;
+use crate::world::{Outcome, World};
+
+// What the screen shows while playing
+pub fn draw_game(world: &World, config: &Config, renderer: &mut dyn Renderer) {
+    renderer.clear(BLACK);
+    world.draw(WHITE, config, renderer);
+    world.score().draw(WHITE, renderer);
+}
+
+// The last moment of the game, with the final score over it
+pub fn draw_game_over(world: &World, config: &Config, renderer: &mut dyn Renderer) {
+    draw_game(world, config, renderer);
+    let (font_offset_x, font_offset_y) = config.font_offset();
+    let text_at = Vec2::new(
+        config.width() / 2.0 + font_offset_x,
+        config.height() / 2.0 + font_offset_y,
+    );
+    let score = format!("Score {}", world.score().points());
+    renderer.text(WHITE, 20, "Game Over", text_at);
+    renderer.text(WHITE, 12, &score, text_at + Vec2::new(0.0, 20.0));
+}
 
 pub trait Scene {
     fn events(
         &mut self,
         rng: &mut dyn RngCore,
-        window: Rc<RefCell<Window>>,
+        window: &mut Window,
         gl: &mut GlGraphics,
         config: &Config,
     ) -> Option<Box<dyn Scene>>;
@@ -26,111 +46,82 @@ pub trait Scene {
 
 #[derive(Clone)]
 pub struct MainScene {
-    difficulty: usize,
-    spaceship: Spaceship,
-    bullets: Vec<Bullet>,
-    astroids: Vec<Astroid>,
+    world: World,
+    replay: Replay,
+    // How many games have been played so far, counting this one
+    game: usize,
+    // Time that's passed and not been stepped through yet, less than a tick
+    unstepped: f64,
 }
 
 impl MainScene {
-    pub fn new(difficulty: usize, config: &Config, rng: &mut dyn RngCore) -> MainScene {
+    pub fn new(
+        difficulty: usize,
+        game: usize,
+        config: &Config,
+        rng: &mut dyn RngCore,
+    ) -> MainScene {
+        let seed = rng.next_u64();
         return MainScene {
-            difficulty: difficulty,
-            spaceship: Spaceship::new(config),
-            bullets: Vec::new(),
-            astroids: repeat(0)
-                .take(difficulty)
-                .map(|_| Astroid::large_new(config, rng))
-                .collect(),
+            world: World::new(difficulty, config, StdRng::seed_from_u64(seed)),
+            replay: Replay::new(difficulty, seed),
+            game: game,
+            unstepped: 0.0,
         };
     }
 
-    fn draw(&self, r: RenderArgs, ds: DrawState, gl: &mut GlGraphics) {
-        use graphics::clear;
-        gl.draw(r.viewport(), |c, gl| {
-            clear(BLACK, gl);
-            for astroid in self.astroids.iter() {
-                astroid.draw(WHITE, &ds, c.transform, gl);
+    // Saves the game so far, if asked to with --record
+    fn save_replay(&self, config: &Config) {
+        if let Some(path) = config.record_path(self.game) {
+            if let Err(message) = self.replay.save(&path) {
+                eprintln!("{}", message);
             }
-            self.spaceship.draw(WHITE, &ds, c.transform, gl);
-            for bullet in self.bullets.iter() {
-                bullet.draw(WHITE, c.transform, gl);
-            }
-        })
+        }
     }
 
-    fn update(
-        &mut self,
-        u: UpdateArgs,
-        rng: &mut dyn RngCore,
-        config: &Config,
-    ) -> Option<Box<dyn Scene>> {
-        self.spaceship.accelerate(u.dt);
-        self.spaceship.turn(u.dt);
-        self.spaceship.go(u.dt, config.width(), config.height());
-        self.spaceship.cooldown(u.dt);
-        for ref mut astroid in self.astroids.iter_mut() {
-            astroid.go(u.dt, config.width(), config.height());
-        }
-        {
-            let astroid_edges = self.astroids.iter().flat_map(|astroid| astroid.edges());
-            if self.spaceship.collides(astroid_edges) {
-                return Some(Box::new(GameOverScene::new(self)));
-            }
-        }
-        if self.spaceship.is_firing() && self.spaceship.ready_to_fire() {
-            self.spaceship.fire(&mut self.bullets);
-        }
-        let mut new_bullets = Vec::with_capacity(self.bullets.len());
-        for bullet in self.bullets.iter_mut() {
-            bullet.go(u.dt, config.width(), config.height());
-            let mut collided = false;
-            self.astroids = self
-                .astroids
-                .iter()
-                .flat_map(|a| {
-                    if bullet.collides(a) {
-                        collided = true;
-                        a.explode(rng)
-                    } else {
-                        vec![a.clone()]
-                    }
-                })
-                .collect();
-            if !collided {
-                new_bullets.push(bullet.clone());
+    fn draw(&self, renderer: &mut dyn Renderer, config: &Config) {
+        draw_game(&self.world, config, renderer);
+    }
+
+    // Steps the world a tick at a time, the same as a replay of it will, for
+    // however many ticks fit in the time that's passed
+    fn update(&mut self, u: UpdateArgs, config: &Config) -> Option<Box<dyn Scene>> {
+        self.unstepped += u.dt;
+        while self.unstepped >= TICK {
+            self.unstepped -= TICK;
+            self.replay.record(self.world.spaceship().controls());
+            match self.world.step(TICK, config) {
+                Outcome::Playing => (),
+                Outcome::ShipDestroyed => {
+                    self.save_replay(config);
+                    return Some(Box::new(GameOverScene::new(self)));
+                }
+                Outcome::WaveCleared => self.world.next_wave(config),
             }
         }
-        new_bullets.retain(Bullet::is_alive);
-        self.bullets = new_bullets;
-        if self.astroids.len() == 0 {
-            return Some(Box::new(MainScene::new(self.difficulty + 1, config, rng)));
-        }
         return None;
     }
 }
 
-const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
-const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
-
 impl Scene for MainScene {
     fn events(
         &mut self,
         mut rng: &mut dyn RngCore,
-        window: Rc<RefCell<Window>>,
+        window: &mut Window,
         gl: &mut GlGraphics,
         config: &Config,
     ) -> Option<Box<dyn Scene>> {
-        let ds = DrawState::default();
-        while let Some(e) = (*window).borrow_mut().next() {
+        for e in window.by_ref() {
             match e {
                 Event::Loop(Loop::Update(u)) => {
-                    let scene_change = self.update(u, rng, config);
+                    let scene_change = self.update(u, config);
                     if scene_change.is_some() {
                         return scene_change;
                     }
                 }
-                Event::Loop(Loop::Render(r)) => self.draw(r, ds, gl),
+                Event::Loop(Loop::Render(r)) => gl.draw(r.viewport(), |c, gl| {
+                    self.draw(&mut GlRenderer::new(c, gl), config);
+                }),
                 Event::Input(
                     Input::Button(ButtonArgs {
                         state: ButtonState::Press,
@@ -139,10 +130,17 @@ impl Scene for MainScene {
                     }),
                     _,
                 ) => {
-                    self.spaceship.handle_press(k);
+                    self.world.spaceship_mut().handle_press(k);
                     match k {
-                        Key::R => return Some(Box::new(MainScene::new(1, config, &mut rng))),
-                        Key::Q => return None,
+                        Key::R => {
+                            self.save_replay(config);
+                            let next = MainScene::new(1, self.game + 1, config, &mut rng);
+                            return Some(Box::new(next));
+                        }
+                        Key::Q => {
+                            self.save_replay(config);
+                            return None;
+                        }
                         _ => (),
                     }
                 }
@@ -154,11 +152,12 @@ impl Scene for MainScene {
                     }),
                     _,
                 ) => {
-                    self.spaceship.handle_release(k);
+                    self.world.spaceship_mut().handle_release(k);
                 }
                 _ => (),
             }
         }
+        self.save_replay(config);
         return None;
     }
 }
@@ -179,32 +178,18 @@ impl Scene for GameOverScene {
     fn events(
         &mut self,
         rng: &mut dyn RngCore,
-        window: Rc<RefCell<Window>>,
+        window: &mut Window,
         gl: &mut GlGraphics,
         config: &Config,
     ) -> Option<Box<dyn Scene>> {
-        let ds = DrawState::default();
-        let game_over_text = Text::new_color(WHITE, 20);
         let font_path = config.font_path();
-        let (font_offset_x, font_offset_y) = config.font_offset();
-        let mut character_cache: Box<GlyphCache> =
-            Box::new(GlyphCache::new(font_path, (), TextureSettings::new()).unwrap());
-        while let Some(e) = (*window).borrow_mut().next() {
+        let mut character_cache = GlyphCache::new(font_path, (), TextureSettings::new()).unwrap();
+        for e in window.by_ref() {
             match e {
                 Event::Loop(Loop::Render(r)) => {
-                    self.end_game.draw(r, ds, gl);
                     gl.draw(r.viewport(), |c, gl| {
-                        game_over_text
-                            .draw(
-                                "Game Over",
-                                character_cache.borrow_mut() as &mut GlyphCache,
-                                &ds,
-                                c.transform
-                                    .trans(config.width() / 2.0, config.height() / 2.0)
-                                    .trans(font_offset_x, font_offset_y),
-                                gl,
-                            )
-                            .unwrap();
+                        let mut renderer = GlRenderer::new(c, gl).with_glyphs(&mut character_cache);
+                        draw_game_over(&self.end_game.world, config, &mut renderer);
                     });
                 }
                 Event::Input(
@@ -216,7 +201,8 @@ impl Scene for GameOverScene {
                     _,
                 ) => match b {
                     Button::Keyboard(Key::Space) | Button::Keyboard(Key::R) => {
-                        return Some(Box::new(MainScene::new(1, config, rng)))
+                        let next = MainScene::new(1, self.end_game.game + 1, config, rng);
+                        return Some(Box::new(next));
                     }
                     Button::Keyboard(Key::Q) => return None,
                     _ => (),
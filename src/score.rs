@@ -0,0 +1,110 @@
+use crate::geometry::Segment;
+use crate::render::{Color, Renderer};
+
+// Points for shooting asteroids and for flying close past them. Getting
+// within range of an asteroid and back out again without crashing is a near
+// miss, which the world keeps track of, and near misses in quick succession build up a combo that multiplies
+// what each one is worth.
+
+const HIT_POINTS: u64 = 10;
+const NEAR_MISS_POINTS: u64 = 25;
+// Seconds after a near miss that the next one adds to the combo
+const COMBO_TIME: f64 = 3.0;
+const MAX_COMBO: u64 = 8;
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Score {
+    points: u64,
+    near_misses: u64,
+    combo: u64,
+    // Seconds left to keep the combo going
+    combo_left: f64,
+}
+
+impl Score {
+    pub fn points(&self) -> u64 {
+        return self.points;
+    }
+
+    pub fn near_misses(&self) -> u64 {
+        return self.near_misses;
+    }
+
+    #[cfg(test)]
+    pub fn combo(&self) -> u64 {
+        return self.combo;
+    }
+
+    pub fn hit(&mut self) {
+        self.points += HIT_POINTS;
+    }
+
+    // Moves the combo on by dt seconds, given whether the ship just got away
+    // from an asteroid it came close to. Returns true for a near miss.
+    pub fn pass(&mut self, escaped: bool, dt: f64) -> bool {
+        self.combo_left = (self.combo_left - dt).max(0.0);
+        if self.combo_left == 0.0 {
+            self.combo = 0;
+        }
+        if !escaped {
+            return false;
+        }
+        self.combo = (self.combo + 1).min(MAX_COMBO);
+        self.combo_left = COMBO_TIME;
+        self.near_misses += 1;
+        self.points += NEAR_MISS_POINTS * self.combo;
+        return true;
+    }
+
+    // The combo meter along the top of the screen: one notch for each step
+    // of the combo, over a bar that shrinks as the time to keep it runs out
+    pub fn draw(&self, color: Color, renderer: &mut dyn Renderer) {
+        if self.combo == 0 {
+            return;
+        }
+        let length = 40.0 * self.combo_left / COMBO_TIME;
+        renderer.line(color, 1.0, Segment::from([4.0, 4.0, 4.0 + length, 4.0]));
+        for i in 0..self.combo {
+            let x = 4.0 + 5.0 * i as f64;
+            renderer.line(color, 1.0, Segment::from([x, 7.0, x, 10.0]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn test_near_miss() {
+        let mut score = Score::default();
+        assert!(!score.pass(false, 0.1));
+        assert!(score.pass(true, 0.1));
+        assert!(!score.pass(false, 0.1));
+        expect!(score.points()).to(be_equal_to(NEAR_MISS_POINTS));
+        expect!(score.near_misses()).to(be_equal_to(1));
+        score.hit();
+        expect!(score.points()).to(be_equal_to(NEAR_MISS_POINTS + HIT_POINTS));
+    }
+
+    #[test]
+    fn test_combo() {
+        let mut score = Score::default();
+        let near_miss = |score: &mut Score, dt: f64| score.pass(true, dt);
+        assert!(near_miss(&mut score, 0.0));
+        assert!(near_miss(&mut score, 1.0));
+        expect!(score.combo()).to(be_equal_to(2));
+        expect!(score.points()).to(be_equal_to(3 * NEAR_MISS_POINTS));
+        // Waiting too long starts the combo over
+        assert!(near_miss(&mut score, COMBO_TIME));
+        expect!(score.combo()).to(be_equal_to(1));
+        score.pass(false, COMBO_TIME);
+        expect!(score.combo()).to(be_equal_to(0));
+        // The combo tops out
+        for _ in 0..2 * MAX_COMBO {
+            near_miss(&mut score, 0.0);
+        }
+        expect!(score.combo()).to(be_equal_to(MAX_COMBO));
+    }
+}
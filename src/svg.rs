@@ -0,0 +1,207 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::geometry::{Segment, Transform, Vec2};
+use crate::render::{Color, Renderer};
+
+// Writes what's drawn out as an SVG, keeping every line and outline as the
+// shape it is, so frames can be zoomed into as far as needed or printed.
+// Transforms become groups, so coordinates are the game's own.
+pub struct SvgRenderer {
+    width: f64,
+    height: f64,
+    // What the frame was last cleared to, under everything else
+    background: String,
+    body: String,
+    // Groups opened by transforms and not closed yet
+    open: Vec<String>,
+}
+
+// A colour as SVG attributes, e.g. `fill="rgb(255,0,0)" fill-opacity="0.5"`
+fn paint(attribute: &str, color: Color) -> String {
+    let [r, g, b, a] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    let mut paint = format!("{}=\"rgb({},{},{})\"", attribute, r, g, b);
+    if a < 255 {
+        write!(paint, " {}-opacity=\"{}\"", attribute, color[3]).unwrap();
+    }
+    return paint;
+}
+
+fn escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+impl SvgRenderer {
+    pub fn new(width: f64, height: f64) -> SvgRenderer {
+        return SvgRenderer {
+            width: width,
+            height: height,
+            background: String::new(),
+            body: String::new(),
+            open: Vec::new(),
+        };
+    }
+
+    // The whole document, closing any groups left open
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = self.width,
+            h = self.height
+        );
+        svg += &self.background;
+        svg += &self.body;
+        svg += &"</g>\n".repeat(self.open.len());
+        svg += "</svg>\n";
+        return svg;
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.to_svg())
+            .map_err(|e| format!("couldn't save {}: {}", path.display(), e));
+    }
+}
+
+impl Renderer for SvgRenderer {
+    // Starts the picture over, keeping any groups that are open
+    fn clear(&mut self, color: Color) {
+        self.background = format!(
+            "<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" {}/>\n",
+            self.width,
+            self.height,
+            paint("fill", color)
+        );
+        self.body = self.open.concat();
+    }
+
+    fn line(&mut self, color: Color, width: f64, line: Segment) {
+        writeln!(
+            self.body,
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {} stroke-width=\"{}\" stroke-linecap=\"round\"/>",
+            line.start.x,
+            line.start.y,
+            line.end.x,
+            line.end.y,
+            paint("stroke", color),
+            width
+        )
+        .unwrap();
+    }
+
+    fn polygon(&mut self, color: Color, points: &[Vec2]) {
+        let points: Vec<String> = points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+        writeln!(
+            self.body,
+            "<polygon points=\"{}\" {}/>",
+            points.join(" "),
+            paint("fill", color)
+        )
+        .unwrap();
+    }
+
+    fn rectangle(&mut self, color: Color, corner: Vec2, size: Vec2) {
+        writeln!(
+            self.body,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+            corner.x,
+            corner.y,
+            size.x,
+            size.y,
+            paint("fill", color)
+        )
+        .unwrap();
+    }
+
+    fn text(&mut self, color: Color, size: u32, text: &str, position: Vec2) {
+        writeln!(
+            self.body,
+            "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" {}>{}</text>",
+            position.x,
+            position.y,
+            size,
+            paint("fill", color),
+            escape(text)
+        )
+        .unwrap();
+    }
+
+    fn push_transform(&mut self, transform: Transform) {
+        let [[a, c, e], [b, d, f]] = transform.matrix();
+        let group = format!(
+            "<g transform=\"matrix({} {} {} {} {} {})\">\n",
+            a, b, c, d, e, f
+        );
+        self.body += &group;
+        self.open.push(group);
+    }
+
+    fn pop_transform(&mut self) {
+        if self.open.pop().is_some() {
+            self.body += "</g>\n";
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::{BLACK, WHITE};
+    use expectest::prelude::*;
+
+    #[test]
+    fn test_shapes() {
+        let mut svg = SvgRenderer::new(200.0, 100.0);
+        svg.line(WHITE, 1.5, Segment::from([1.0, 2.0, 3.0, 4.5]));
+        svg.polygon(
+            [1.0, 0.0, 0.0, 0.5],
+            &[Vec2::zero(), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)],
+        );
+        svg.rectangle(BLACK, Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0));
+        svg.text(WHITE, 12, "Score <3 & \"more\"", Vec2::new(5.0, 6.0));
+        expect!(svg.to_svg()).to(be_equal_to(
+            [
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"100\" viewBox=\"0 0 200 100\">",
+                "<line x1=\"1\" y1=\"2\" x2=\"3\" y2=\"4.5\" stroke=\"rgb(255,255,255)\" stroke-width=\"1.5\" stroke-linecap=\"round\"/>",
+                "<polygon points=\"0,0 1,0 0,1\" fill=\"rgb(255,0,0)\" fill-opacity=\"0.5\"/>",
+                "<rect x=\"1\" y=\"2\" width=\"3\" height=\"4\" fill=\"rgb(0,0,0)\"/>",
+                "<text x=\"5\" y=\"6\" font-family=\"sans-serif\" font-size=\"12\" fill=\"rgb(255,255,255)\">Score &lt;3 &amp; &quot;more&quot;</text>",
+                "</svg>",
+                "",
+            ]
+            .join("\n"),
+        ));
+    }
+
+    #[test]
+    fn test_transforms() {
+        let mut svg = SvgRenderer::new(10.0, 10.0);
+        svg.push_transform(Transform::translation(Vec2::new(3.0, 4.0)));
+        svg.push_transform(Transform::scale(2.0));
+        svg.pop_transform();
+        let svg_text = svg.to_svg();
+        assert!(svg_text.contains("<g transform=\"matrix(1 0 0 1 3 4)\">\n"));
+        assert!(svg_text.contains("<g transform=\"matrix(2 0 0 2 0 0)\">\n</g>\n"));
+        // Groups left open are closed, and extra pops are ignored
+        expect!(svg_text.matches("</g>").count()).to(be_equal_to(2));
+        svg.pop_transform();
+        svg.pop_transform();
+        expect!(svg.to_svg().matches("</g>").count()).to(be_equal_to(2));
+        // Clearing keeps the groups that are open, but the background
+        // isn't in them
+        svg.push_transform(Transform::scale(2.0));
+        svg.line(WHITE, 1.0, Segment::from([0.0, 0.0, 1.0, 1.0]));
+        svg.clear(BLACK);
+        let cleared = svg.to_svg();
+        assert!(cleared.contains(concat!(
+            "<rect x=\"0\" y=\"0\" width=\"10\" height=\"10\" fill=\"rgb(0,0,0)\"/>\n",
+            "<g transform=\"matrix(2 0 0 2 0 0)\">\n</g>\n"
+        )));
+        expect!(cleared.matches("<g").count()).to(be_equal_to(1));
+        assert!(!cleared.contains("<line"));
+    }
+}
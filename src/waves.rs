@@ -0,0 +1,43 @@
+use crate::actors::AstroidKind;
+use crate::actors::AstroidKind::{Armored, Explosive, Magnetic, Plain, Splitter};
+
+// The large asteroids each wave starts with. Waves past the end of the list
+// get one asteroid per wave number, cycling through every kind.
+const WAVES: &[&[AstroidKind]] = &[
+    &[Plain],
+    &[Plain, Plain],
+    &[Plain, Plain, Armored],
+    &[Plain, Splitter, Plain, Splitter],
+    &[Plain, Explosive, Armored, Plain, Plain],
+    &[Magnetic, Plain, Splitter, Plain, Explosive, Plain],
+    &[
+        Armored, Magnetic, Plain, Explosive, Splitter, Plain, Magnetic,
+    ],
+];
+
+const CYCLE: [AstroidKind; 5] = [Plain, Armored, Explosive, Splitter, Magnetic];
+
+pub fn wave(difficulty: usize) -> Vec<AstroidKind> {
+    if let Some(kinds) = WAVES.get(difficulty.saturating_sub(1)) {
+        return kinds.to_vec();
+    }
+    return CYCLE.iter().cycle().take(difficulty).cloned().collect();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn test_wave_sizes() {
+        for difficulty in 1..20 {
+            expect!(wave(difficulty).len()).to(be_equal_to(difficulty));
+        }
+    }
+
+    #[test]
+    fn test_first_wave_is_plain() {
+        expect!(wave(1)).to(be_equal_to(vec![Plain]));
+    }
+}
@@ -0,0 +1,785 @@
+use rand::rngs::StdRng;
+
+use crate::actors::{Astroid, Bullet, Controls, Spaceship, SPACESHIP_RADIUS};
+use crate::bounds::Boundary;
+use crate::config::Config;
+use crate::geometry::Vec2;
+use crate::gravity::total_pull;
+use crate::grid::{circles_overlap, SpatialGrid};
+use crate::physics::collide_astroids;
+use crate::raycast::{self, Entity, Mask};
+use crate::render::{Color, Renderer};
+use crate::score::Score;
+use crate::waves::wave;
+
+// Everything in one game, with nothing tying it to a window, so that many
+// can run side by side. Each world has its own random number generator.
+#[derive(Clone)]
+pub struct World {
+    difficulty: usize,
+    spaceship: Spaceship,
+    bullets: Vec<Bullet>,
+    astroids: Vec<Astroid>,
+    grid: SpatialGrid,
+    scratch: Scratch,
+    rng: StdRng,
+    // Asteroids hit so far, by bullets or blasts
+    hits: u64,
+    score: Score,
+    // Asteroids that were within near miss range of the ship when it was
+    // last checked, by index
+    near: Vec<usize>,
+}
+
+// How a tick ended
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Playing,
+    ShipDestroyed,
+    WaveCleared,
+}
+
+// A bullet hitting an asteroid partway through a tick
+#[derive(Clone)]
+struct Hit {
+    astroid: usize,
+    // Index of the bullet among those still flying
+    bullet: usize,
+    time: f64,
+    impact: Vec2,
+    heading: f64,
+}
+
+// Buffers that are emptied and refilled every tick, kept around so that a
+// tick where nothing breaks doesn't need any new memory.
+#[derive(Clone, Default)]
+struct Scratch {
+    nearby: Vec<usize>,
+    hits: Vec<Hit>,
+    // Bullets used up by hits, by index
+    spent: Vec<usize>,
+    in_range: Vec<usize>,
+    blasts: Vec<Vec2>,
+    fragments: Vec<Astroid>,
+    ray: raycast::Scratch,
+}
+
+// Tries at placing each asteroid of a new wave clear of the ship
+const SAFE_SPAWN_ATTEMPTS: usize = 10;
+
+// Removes the asteroid the way swap_remove does, keeping `near` pointing at
+// the same asteroids. An asteroid that's gone can't be got away from, so it's
+// dropped from `near` rather than counting as a near miss.
+fn remove_astroid(astroids: &mut Vec<Astroid>, near: &mut Vec<usize>, i: usize) {
+    let last = astroids.len() - 1;
+    near.retain(|&n| n != i);
+    for n in near.iter_mut() {
+        if *n == last {
+            *n = i;
+        }
+    }
+    astroids.swap_remove(i);
+}
+
+impl World {
+    pub fn new(difficulty: usize, config: &Config, rng: StdRng) -> World {
+        let mut world = World {
+            difficulty: difficulty,
+            spaceship: Spaceship::new(config),
+            bullets: Vec::new(),
+            astroids: Vec::new(),
+            grid: SpatialGrid::new(&config.bounds(), config.grid_cell_size()),
+            scratch: Scratch::default(),
+            rng: rng,
+            hits: 0,
+            score: Score::default(),
+            near: Vec::new(),
+        };
+        world.start_wave(difficulty, config);
+        return world;
+    }
+
+    // Clears the field and starts over at the given wave with a fresh ship
+    pub fn start_wave(&mut self, difficulty: usize, config: &Config) {
+        self.difficulty = difficulty;
+        self.spaceship = Spaceship::new(config);
+        self.bullets.clear();
+        self.astroids.clear();
+        self.near.clear();
+        let bounds = config.bounds();
+        let spawn = self.spaceship.position();
+        for kind in wave(difficulty) {
+            // Asteroids too close to where the ship starts are placed again,
+            // up to a few times
+            let mut astroid = Astroid::large_new(kind, config, &mut self.rng);
+            for _ in 1..SAFE_SPAWN_ATTEMPTS {
+                if astroid.distance_from(spawn, &bounds) >= config.safe_spawn_distance() {
+                    break;
+                }
+                astroid = Astroid::large_new(kind, config, &mut self.rng);
+            }
+            self.astroids.push(astroid);
+        }
+    }
+
+    pub fn next_wave(&mut self, config: &Config) {
+        self.start_wave(self.difficulty + 1, config);
+    }
+
+    pub fn spaceship(&self) -> &Spaceship {
+        return &self.spaceship;
+    }
+
+    pub fn spaceship_mut(&mut self) -> &mut Spaceship {
+        return &mut self.spaceship;
+    }
+
+    pub fn astroids(&self) -> &[Astroid] {
+        return &self.astroids;
+    }
+
+    pub fn hits(&self) -> u64 {
+        return self.hits;
+    }
+
+    pub fn score(&self) -> &Score {
+        return &self.score;
+    }
+
+    pub fn steer(&mut self, controls: Controls) {
+        self.spaceship.steer(controls);
+    }
+
+    // The first thing in `mask` along the ray from `origin` within `max_dist`,
+    // which has to be finite. Only things whose bounding circles the ray
+    // passes through have their edges checked.
+    pub fn raycast(
+        &mut self,
+        origin: Vec2,
+        direction: Vec2,
+        max_dist: f64,
+        mask: Mask,
+        config: &Config,
+    ) -> Option<raycast::Hit> {
+        let bounds = config.bounds();
+        let direction = direction.normalize()?;
+        if max_dist <= 0.0 || !max_dist.is_finite() {
+            return None;
+        }
+        let raycast::Scratch { pieces, edges } = &mut self.scratch.ray;
+        raycast::pieces(&bounds, origin, direction, max_dist, pieces);
+        edges.clear();
+        if mask.contains(Mask::ASTROIDS) {
+            for (i, astroid) in self.astroids.iter().enumerate() {
+                let centre = astroid.obj().position();
+                for offset in astroid.ghosts(0.0, &bounds) {
+                    if raycast::passes_near(pieces, centre + offset, astroid.radius()) {
+                        let astroid_edges = astroid.edges_at(offset);
+                        edges.extend(astroid_edges.map(|edge| (Entity::Astroid(i), edge)));
+                    }
+                }
+            }
+        }
+        if mask.contains(Mask::SHIPS) {
+            let ship = Entity::Ship(self.spaceship.owner());
+            let ship_edges = self.spaceship.wrapped_edges(&bounds);
+            edges.extend(ship_edges.map(|edge| (ship, edge)));
+        }
+        if mask.contains(Mask::WALLS) {
+            let walls = config.arena().walls().iter().enumerate();
+            edges.extend(walls.map(|(i, &wall)| (Entity::Wall(i), wall)));
+        }
+        return raycast::first_hit(pieces, direction, edges);
+    }
+
+    pub fn draw(&self, color: Color, config: &Config, renderer: &mut dyn Renderer) {
+        let bounds = config.bounds();
+        config.arena().draw(color, renderer);
+        for astroid in self.astroids.iter() {
+            astroid.draw(color, &bounds, renderer);
+        }
+        self.spaceship.draw(color, &bounds, renderer);
+        for bullet in self.bullets.iter() {
+            bullet.draw(color, &bounds, renderer);
+        }
+    }
+
+    // Hits everything within the explosion radius of each blast, setting off
+    // any explosive asteroids caught in it. Returns true if the ship was caught.
+    fn detonate(&mut self, config: &Config) -> bool {
+        let radius = config.explosion_radius();
+        let bounds = config.bounds();
+        let offset = |from: Vec2, to: Vec2| bounds.delta(from, to);
+        let mut ship_caught = false;
+        while let Some(center) = self.scratch.blasts.pop() {
+            let to_ship = offset(center, self.spaceship.position());
+            ship_caught |= to_ship.dot(to_ship) < radius * radius;
+            // Backwards, so that removing an asteroid only moves one that's
+            // already been looked at
+            for i in (0..self.astroids.len()).rev() {
+                let a = &mut self.astroids[i];
+                let position = a.obj().position();
+                let d = offset(center, position);
+                if d.dot(d) >= radius * radius {
+                    continue;
+                }
+                if a.is_explosive() {
+                    self.scratch.blasts.push(position);
+                }
+                let heading = d.heading();
+                self.hits += 1;
+                self.score.hit();
+                if !a.hit(
+                    position,
+                    heading,
+                    &mut self.rng,
+                    &mut self.scratch.fragments,
+                ) {
+                    remove_astroid(&mut self.astroids, &mut self.near, i);
+                }
+            }
+            self.astroids.append(&mut self.scratch.fragments);
+        }
+        return ship_caught;
+    }
+
+    // Breaks every asteroid that was hit this tick, in place. An asteroid hit
+    // by several bullets at once takes them one at a time, so they can wear
+    // through its armor, and the bullets are used up until it breaks.
+    fn apply_hits(&mut self) {
+        let (hits, spent) = (&mut self.scratch.hits, &mut self.scratch.spent);
+        // Backwards through the asteroids, for the same reason as in
+        // detonate, and through each one's hits in the order they happened
+        hits.sort_unstable_by(|a, b| b.astroid.cmp(&a.astroid).then(a.time.total_cmp(&b.time)));
+        let mut broken = None;
+        for hit in hits.drain(..) {
+            // Bullets that reach an asteroid after it's broken fly on through
+            // where it was
+            if broken == Some(hit.astroid) {
+                continue;
+            }
+            spent.push(hit.bullet);
+            let astroid = &mut self.astroids[hit.astroid];
+            if astroid.is_explosive() {
+                self.scratch.blasts.push(astroid.obj().position());
+            }
+            self.hits += 1;
+            self.score.hit();
+            if !astroid.hit(
+                hit.impact,
+                hit.heading,
+                &mut self.rng,
+                &mut self.scratch.fragments,
+            ) {
+                remove_astroid(&mut self.astroids, &mut self.near, hit.astroid);
+                broken = Some(hit.astroid);
+            }
+        }
+        self.astroids.append(&mut self.scratch.fragments);
+        spent.sort_unstable();
+        let mut i = 0;
+        self.bullets.retain(|_| {
+            i += 1;
+            return spent.binary_search(&(i - 1)).is_err();
+        });
+        spent.clear();
+    }
+
+    // Moves everything on by dt seconds
+    pub fn step(&mut self, dt: f64, config: &Config) -> Outcome {
+        let bounds = config.bounds();
+        let arena = config.arena();
+        let pull = |p: Vec2| total_pull(arena.wells(), p, &bounds);
+        self.spaceship.accelerate(dt);
+        self.spaceship.turn(dt);
+        self.spaceship
+            .apply_acceleration(pull(self.spaceship.position()));
+        if !self.spaceship.go(dt, &bounds) || arena.swallows(self.spaceship.position(), &bounds) {
+            return Outcome::ShipDestroyed;
+        }
+        self.spaceship.cooldown(dt);
+        let ship_position = self.spaceship.position();
+        let mut lost = Vec::new();
+        let (near, mut index, mut kept) = (&mut self.near, 0, 0);
+        self.astroids.retain_mut(|astroid| {
+            astroid.steer_toward(ship_position, config.magnetic_pull(), dt, &bounds);
+            let acceleration = pull(astroid.obj().position());
+            astroid.obj_mut().apply_acceleration(acceleration);
+            let inside = astroid.go(dt, &bounds);
+            if !inside {
+                lost.push((astroid.size(), astroid.kind()));
+            }
+            let keep = inside && !arena.swallows(astroid.obj().position(), &bounds);
+            // Keeps `near` pointing at the same asteroids, the same as
+            // remove_astroid
+            if let Some(n) = near.iter().position(|&n| n == index) {
+                if keep {
+                    near[n] = kept;
+                } else {
+                    near.swap_remove(n);
+                }
+            }
+            index += 1;
+            kept += keep as usize;
+            return keep;
+        });
+        if bounds.boundary == Boundary::Open {
+            for (size, kind) in lost {
+                self.astroids
+                    .push(Astroid::stream_in(size, kind, config, &mut self.rng));
+            }
+        }
+        if config.astroid_collisions() {
+            collide_astroids(
+                &mut self.astroids,
+                &bounds,
+                &mut self.grid,
+                &mut self.scratch.nearby,
+            );
+        }
+        let ship_velocity = arena.deflect(
+            self.spaceship.position(),
+            self.spaceship.velocity(),
+            self.spaceship.edges().into_iter(),
+        );
+        self.spaceship.set_velocity(ship_velocity);
+        for astroid in self.astroids.iter_mut() {
+            let velocity = arena.deflect(
+                astroid.obj().position(),
+                astroid.obj().velocity(),
+                astroid.edges(),
+            );
+            astroid.obj_mut().set_velocity(velocity);
+        }
+        // Each asteroid covers everywhere it passed through this tick
+        self.grid.clear();
+        for (i, astroid) in self.astroids.iter().enumerate() {
+            let reach = astroid.radius() + astroid.obj().velocity().length() * dt;
+            self.grid.insert(i, astroid.obj().position(), reach);
+        }
+        let nearby = &mut self.scratch.nearby;
+        let ship_position = self.spaceship.position();
+        let ship_reach = SPACESHIP_RADIUS + self.spaceship.velocity().length() * dt;
+        self.grid.query(ship_position, ship_reach, nearby);
+        let ship_hit = nearby.iter().any(|&i| {
+            let astroid = &self.astroids[i];
+            let motion = (self.spaceship.velocity() - astroid.obj().velocity()) * dt;
+            let reach = SPACESHIP_RADIUS + motion.length();
+            let position = astroid.obj().position();
+            if !circles_overlap(&bounds, ship_position, reach, position, astroid.radius()) {
+                return false;
+            }
+            // Overlapping now, even from inside, or swept through during the tick
+            let edges = astroid.wrapped_edges(reach, &bounds);
+            self.spaceship.contact(astroid, &bounds).is_some()
+                || self.spaceship.time_of_impact(motion, edges).is_some()
+        });
+        if ship_hit {
+            return Outcome::ShipDestroyed;
+        }
+        // Getting close to an asteroid and away from it again, while it's
+        // still there, is a near miss
+        let near_miss = config.near_miss_distance();
+        self.grid
+            .query(ship_position, SPACESHIP_RADIUS + near_miss, nearby);
+        let in_range = &mut self.scratch.in_range;
+        in_range.clear();
+        in_range.extend(
+            nearby
+                .iter()
+                .filter(|&&i| self.spaceship.distance_to(&self.astroids[i], &bounds) < near_miss),
+        );
+        let escaped = self.near.iter().any(|i| !in_range.contains(i));
+        std::mem::swap(&mut self.near, in_range);
+        self.score.pass(escaped, dt);
+        if self.spaceship.is_firing() && self.spaceship.ready_to_fire() {
+            self.spaceship.fire(&mut self.bullets);
+        }
+        let (grid, astroids, hits) = (&self.grid, &self.astroids, &mut self.scratch.hits);
+        let mut kept = 0;
+        self.bullets.retain_mut(|bullet| {
+            let from = bullet.coords();
+            bullet.apply_acceleration(pull(from));
+            let inside = bullet.go(dt, &bounds)
+                && arena.clear_path(from, from + bounds.delta(from, bullet.coords()))
+                && !arena.swallows(bullet.coords(), &bounds);
+            if !inside {
+                return false;
+            }
+            // The bullet stops at the first asteroid in its path, unless
+            // another bullet breaks it first
+            let path_centre = bullet.position_at(0.5);
+            let path_reach = (bullet.coords() - path_centre).length();
+            grid.query(path_centre, path_reach, nearby);
+            let first_hit = nearby
+                .iter()
+                .filter_map(|&i| {
+                    let t = bullet.time_of_impact(&astroids[i], &bounds)?;
+                    Some((i, t))
+                })
+                .min_by(|(_, t1), (_, t2)| t1.total_cmp(t2));
+            if let Some((i, t)) = first_hit {
+                // The bullet may have hit a copy of the asteroid across an
+                // edge, so the impact is moved next to the asteroid itself
+                let centre = astroids[i].obj().position();
+                hits.push(Hit {
+                    astroid: i,
+                    bullet: kept,
+                    time: t,
+                    impact: centre + bounds.delta(centre, bullet.position_at(t)),
+                    heading: bullet.heading(),
+                });
+                kept += 1;
+                return true;
+            }
+            let alive = bullet.is_alive();
+            kept += alive as usize;
+            return alive;
+        });
+        self.apply_hits();
+        let friendly_fire = config.friendly_fire();
+        if self
+            .bullets
+            .iter()
+            .any(|bullet| self.spaceship.hit_by(bullet, friendly_fire))
+        {
+            return Outcome::ShipDestroyed;
+        }
+        if self.detonate(config) {
+            return Outcome::ShipDestroyed;
+        }
+        if self.astroids.is_empty() {
+            return Outcome::WaveCleared;
+        }
+        return Outcome::Playing;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actors::{GameObject, PLAYER};
+    use crate::geometry::Polygon;
+    use expectest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // Counts allocations made by each thread, so tests running alongside each
+    // other don't throw off the count.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count_allocation() {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation();
+            return System.alloc(layout);
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation();
+            return System.realloc(ptr, layout, new_size);
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations() -> usize {
+        return ALLOCATIONS.with(Cell::get);
+    }
+
+    #[test]
+    fn test_raycast() {
+        let config = Config::new();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(40));
+        // The ship is in the middle, with an asteroid straddling the top edge
+        world.astroids = vec![Astroid::with_border(
+            GameObject::new(Vec2::new(100.0, 2.0), Vec2::zero()),
+            2,
+            Polygon::square(5.0),
+        )];
+        let up = Vec2::new(0.0, -1.0);
+        let from_ship = Vec2::new(100.0, 80.0);
+        let hit = world
+            .raycast(from_ship, up, 200.0, Mask::ALL, &config)
+            .unwrap();
+        expect!(hit.entity).to(be_equal_to(Entity::Astroid(0)));
+        expect!(hit.distance).to(be_close_to(73.0));
+        expect!(hit.normal).to(be_equal_to(Vec2::new(0.0, 1.0)));
+        // Looking down, the ray goes round the screen and meets the bottom of
+        // the asteroid's copy at the top
+        let down = Vec2::new(0.0, 1.0);
+        let hit = world
+            .raycast(from_ship, down, 200.0, Mask::ASTROIDS, &config)
+            .unwrap();
+        expect!(hit.distance).to(be_close_to(117.0));
+        expect!(hit.point.y).to(be_close_to(197.0));
+        // Looking up from below, the ship is in the way unless it's masked out
+        let below = Vec2::new(100.0, 150.0);
+        let hit = world.raycast(below, up, 200.0, Mask::ALL, &config).unwrap();
+        expect!(hit.entity).to(be_equal_to(Entity::Ship(PLAYER)));
+        let hit = world.raycast(below, up, 200.0, Mask::ASTROIDS | Mask::WALLS, &config);
+        expect!(hit.unwrap().entity).to(be_equal_to(Entity::Astroid(0)));
+        expect!(world.raycast(below, up, 40.0, Mask::ALL, &config)).to(be_none());
+        // Rays that go on forever around the screen aren't cast at all
+        let forever = world.raycast(below, up, f64::INFINITY, Mask::ALL, &config);
+        expect!(forever).to(be_none());
+        // Casting again reuses the buffers from before
+        let before = allocations();
+        world.raycast(from_ship, down, 200.0, Mask::ALL, &config);
+        expect!(allocations() - before).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_cuts_across_edges() {
+        let config = Config::new();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(42));
+        // Straddling the right edge, so its copy on the left reaches x = 8,
+        // with a bullet flying up through the copy 5 right of its centre
+        world.astroids = vec![Astroid::with_border(
+            GameObject::new(Vec2::new(198.0, 100.0), Vec2::zero()),
+            2,
+            Polygon::square(10.0),
+        )];
+        let weapon = config.weapon();
+        let bullet = Bullet::new(Vec2::new(3.0, 130.0), 0.0, Vec2::zero(), &weapon, PLAYER);
+        world.bullets = vec![bullet];
+        while world.hits() == 0 {
+            expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        }
+        // Cut where the bullet went, not through the middle
+        let mut areas: Vec<f64> = world.astroids.iter().map(Astroid::mass).collect();
+        areas.sort_by(|a, b| a.total_cmp(b));
+        expect!(areas.len()).to(be_equal_to(2));
+        expect!(areas[0]).to(be_close_to(100.0));
+        expect!(areas[1]).to(be_close_to(300.0));
+    }
+
+    // Near misses from an asteroid sliding past under the ship, 8 below its
+    // tail, for three seconds
+    fn near_misses(args: &[&str]) -> u64 {
+        let config = Config::from_args(args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(41));
+        world.astroids = vec![Astroid::with_border(
+            GameObject::new(Vec2::new(60.0, 120.0), Vec2::new(40.0, 0.0)),
+            2,
+            Polygon::square(5.0),
+        )];
+        for _ in 0..360 {
+            expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        }
+        return world.score().near_misses();
+    }
+
+    #[test]
+    fn test_near_miss() {
+        expect!(near_misses(&[])).to(be_equal_to(1));
+        // Further away than that doesn't count
+        expect!(near_misses(&["--near-miss", "5"])).to(be_equal_to(0));
+    }
+
+    // Three bullets side by side flying up into the asteroid, all hitting it
+    // on the same tick. Returns the world just after.
+    fn volley(astroid: Astroid) -> World {
+        let config = Config::new();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(44));
+        world.astroids = vec![astroid];
+        let weapon = config.weapon();
+        world.bullets = [46.0, 50.0, 54.0]
+            .iter()
+            .map(|&x| Bullet::new(Vec2::new(x, 80.0), 0.0, Vec2::zero(), &weapon, PLAYER))
+            .collect();
+        while world.hits() == 0 {
+            expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        }
+        return world;
+    }
+
+    #[test]
+    fn test_several_hits_at_once() {
+        let obj = GameObject::new(Vec2::new(50.0, 50.0), Vec2::zero());
+        // Every bullet wears away a layer of armor, and the last one breaks it
+        let world = volley(Astroid::armored_with_border(
+            obj,
+            2,
+            3,
+            Polygon::square(10.0),
+        ));
+        expect!(world.hits()).to(be_equal_to(3));
+        expect!(world.astroids.len()).to(be_equal_to(2));
+        expect!(world.bullets.len()).to(be_equal_to(0));
+        // Once it's broken, the rest fly on
+        let world = volley(Astroid::with_border(obj, 2, Polygon::square(10.0)));
+        expect!(world.hits()).to(be_equal_to(1));
+        expect!(world.astroids.len()).to(be_equal_to(2));
+        expect!(world.bullets.len()).to(be_equal_to(2));
+    }
+
+    #[test]
+    fn test_shooting_a_near_asteroid_isnt_a_near_miss() {
+        let config = Config::new();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(43));
+        // Small enough to be destroyed outright, 14 in front of the nose
+        world.astroids = vec![Astroid::with_border(
+            GameObject::new(Vec2::new(100.0, 70.0), Vec2::zero()),
+            1,
+            Polygon::square(3.0),
+        )];
+        expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        world.spaceship.fire(&mut world.bullets);
+        for _ in 0..60 {
+            world.step(1.0 / 120.0, &config);
+        }
+        expect!(world.hits()).to(be_equal_to(1));
+        expect!(world.score().near_misses()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_no_time_passing() {
+        let config = Config::new();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(45));
+        world.steer(Controls {
+            thrust: 1.0,
+            turn: 1.0,
+            fire: false,
+        });
+        expect!(world.step(0.0, &config)).to(be_equal_to(Outcome::Playing));
+        expect!(world.spaceship().position()).to(be_equal_to(Vec2::new(100.0, 100.0)));
+    }
+
+    #[test]
+    fn test_swallowed_asteroid_isnt_a_near_miss() {
+        let arena = ["--arena", "res/arenas/black_hole.txt"];
+        let config = Config::from_args(arena.iter().map(|arg| arg.to_string())).unwrap();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(46));
+        // Big enough to still be within range of the ship, below the black
+        // hole, as it falls in
+        world.astroids = vec![Astroid::with_border(
+            GameObject::new(Vec2::new(100.0, 115.0), Vec2::new(0.0, -20.0)),
+            3,
+            Polygon::square(30.0),
+        )];
+        expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        expect!(world.near.len()).to(be_equal_to(1));
+        let mut outcome = Outcome::Playing;
+        for _ in 0..120 {
+            outcome = world.step(1.0 / 120.0, &config);
+            if outcome != Outcome::Playing {
+                break;
+            }
+        }
+        expect!(outcome).to(be_equal_to(Outcome::WaveCleared));
+        expect!(world.score().near_misses()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_near_miss_beside_a_broken_asteroid() {
+        let config = Config::new();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(47));
+        // One asteroid sliding past under the ship, and a small one in front
+        // of its nose that gets shot while the other's in range
+        world.astroids = vec![
+            Astroid::with_border(
+                GameObject::new(Vec2::new(60.0, 120.0), Vec2::new(40.0, 0.0)),
+                2,
+                Polygon::square(5.0),
+            ),
+            Astroid::with_border(
+                GameObject::new(Vec2::new(100.0, 70.0), Vec2::zero()),
+                1,
+                Polygon::square(3.0),
+            ),
+        ];
+        for _ in 0..90 {
+            expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        }
+        expect!(world.near.len()).to(be_equal_to(2));
+        world.spaceship.fire(&mut world.bullets);
+        while world.hits() == 0 {
+            expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        }
+        expect!(world.near.clone()).to(be_equal_to(vec![0]));
+        expect!(world.score().near_misses()).to(be_equal_to(0));
+        for _ in 0..240 {
+            expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        }
+        expect!(world.score().near_misses()).to(be_equal_to(1));
+    }
+
+    #[test]
+    fn test_waves_start_clear_of_the_ship() {
+        let config = Config::new();
+        let bounds = config.bounds();
+        for seed in 0..20 {
+            let world = World::new(4, &config, StdRng::seed_from_u64(seed));
+            let spawn = world.spaceship.position();
+            for astroid in world.astroids.iter() {
+                assert!(astroid.distance_from(spawn, &bounds) >= config.safe_spawn_distance());
+            }
+        }
+    }
+
+    #[test]
+    fn test_steady_ticks_dont_allocate() {
+        let arg = "--astroid-collisions".to_string();
+        let config = Config::from_args(std::iter::once(arg)).unwrap();
+        let mut world = World::new(1, &config, StdRng::seed_from_u64(37));
+        // One asteroid drifting along a row below the ship and one down a
+        // column beside it, so nothing ever gets hit. A third sits still
+        // just behind the ship with an edge on the same line as the ship's
+        // right side, so checking how close they are is a close call.
+        let collinear = Polygon::new(vec![
+            Vec2::new(-4.0, -4.0),
+            Vec2::new(-3.0, 0.0),
+            Vec2::new(2.0, -3.0),
+        ]);
+        world.astroids = vec![
+            Astroid::with_border(
+                GameObject::new(Vec2::new(20.0, 160.0), Vec2::new(20.0, 0.0)),
+                2,
+                Polygon::square(5.0),
+            ),
+            Astroid::with_border(
+                GameObject::new(Vec2::new(40.0, 20.0), Vec2::new(0.0, 20.0)),
+                2,
+                Polygon::square(5.0),
+            ),
+            Astroid::with_border(
+                GameObject::new(Vec2::new(110.0, 115.0), Vec2::zero()),
+                1,
+                collinear,
+            ),
+        ];
+        let step = |world: &mut World| {
+            expect!(world.step(1.0 / 120.0, &config)).to(be_equal_to(Outcome::Playing));
+        };
+        // Long enough for both asteroids to go all the way around, so the
+        // buffers have grown as big as they need to be.
+        world.spaceship.fire(&mut world.bullets);
+        for _ in 0..1200 {
+            step(&mut world);
+        }
+        world.spaceship.fire(&mut world.bullets);
+
+        let before = allocations();
+        for _ in 0..60 {
+            step(&mut world);
+        }
+        expect!(allocations() - before).to(be_equal_to(0));
+        expect!(world.astroids.len()).to(be_equal_to(3));
+        expect!(world.bullets.len()).to(be_equal_to(1));
+    }
+}